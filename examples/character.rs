@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use soft2d::{core::*, image::*, window::*};
+use soft2d::{core::*, draw_list::*, image::*, text::*, window::*};
 
 const SRC_TILE_SIZE: i32 = 48;
 const PLAYER_SIZE: f32 = 0.5;
@@ -84,8 +84,9 @@ enum AnimationKind {
 }
 
 impl AnimationKind {
-    const fn animation(self, direction: Direction) -> Animation {
-        ANIMATIONS[self as usize * 2 + direction as usize]
+    // Left-facing frames are drawn by mirroring the right-facing ones.
+    const fn animation(self) -> Animation {
+        ANIMATIONS[self as usize * 2 + Direction::Right as usize]
     }
 }
 
@@ -157,7 +158,7 @@ impl Player {
         Self {
             pos,
             image,
-            animation: animation_kind.animation(direction),
+            animation: animation_kind.animation(),
             animation_kind,
             direction,
             shoot: None,
@@ -205,8 +206,8 @@ impl Player {
                 animation_kind = AnimationKind::Shoot;
                 self.shoot = Some(Timer::new(0.5));
             }
-            if animation_kind != self.animation_kind || direction != self.direction {
-                self.animation = animation_kind.animation(direction);
+            if animation_kind != self.animation_kind {
+                self.animation = animation_kind.animation();
             }
             self.animation_kind = animation_kind;
             self.direction = direction;
@@ -216,22 +217,29 @@ impl Player {
         self.animation.update(dt);
     }
 
-    fn render(&self, buffer: &mut Buffer, scale: f32, camera_offset: IVec2) {
+    fn record<'a>(&'a self, list: &mut DrawList<'a>, scale: f32, camera_offset: IVec2) {
         let player_size = IVec2::splat((scale * PLAYER_SIZE) as i32);
         let camera_offset = camera_offset - player_size / 2;
         let player_pos = camera_offset + (self.pos * scale).as_ivec2();
-        buffer.blit(
-            &self.image,
-            Some(self.animation.src_pos()),
-            Some(IVec2::splat(SRC_TILE_SIZE)),
-            Some(player_pos),
-            Some(player_size),
+        list.push(
+            0,
+            self.pos.y,
+            DrawCommand::Blit {
+                image: &self.image,
+                src: Rect::new(self.animation.src_pos(), IVec2::splat(SRC_TILE_SIZE)),
+                dst: Rect::new(player_pos, player_size),
+                options: BlitOptions {
+                    flip_x: self.direction == Direction::Left,
+                    ..Default::default()
+                },
+            },
         );
     }
 }
 
 struct Character {
     players: Vec<Player>,
+    font: BitmapFont,
 }
 
 impl Character {
@@ -249,6 +257,7 @@ impl Character {
                     InputConfig::new_player2(),
                 ),
             ],
+            font: BitmapFont::default(),
         }
     }
 }
@@ -262,12 +271,26 @@ impl State for Character {
         let size = window.size();
         let scale = size.y.min(size.x) as f32;
         let camera_offset = size / 2;
-        let mut buffer = window.buffer();
-        buffer.clear(Color::LIGHT_GRAY);
-        self.players.sort_by(|a, b| a.pos.y.total_cmp(&b.pos.y));
+        let fps = format!("FPS: {}", window.fps());
+        let mut list = DrawList::new();
+        list.push(-1, 0.0, DrawCommand::Clear(Color::LIGHT_GRAY));
         for player in self.players.iter() {
-            player.render(&mut buffer, scale, camera_offset);
+            player.record(&mut list, scale, camera_offset);
         }
+        list.push(
+            1,
+            0.0,
+            DrawCommand::Text {
+                font: &self.font,
+                pos: ivec2(4, 4),
+                color: Color::BLACK,
+                text: fps,
+            },
+        );
+        list.sort();
+
+        let mut buffer = window.buffer();
+        list.replay(&mut buffer);
         buffer.present();
     }
 }
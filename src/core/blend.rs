@@ -0,0 +1,212 @@
+use crate::core::*;
+
+use super::premul::mul;
+use super::srgb;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    Replace,
+    #[default]
+    AlphaTest,
+    SourceOver,
+    Additive,
+    Multiply,
+    Screen,
+    Subtract,
+}
+
+impl BlendMode {
+    pub fn blend(self, dst: Color, src: Color) -> Color {
+        match self {
+            Self::Replace => src,
+            Self::AlphaTest => {
+                if src.a() != 0x00 {
+                    src
+                } else {
+                    dst
+                }
+            }
+            Self::SourceOver if src.a() == 0x00 => dst,
+            Self::SourceOver if src.a() == 0xFF => src,
+            _ => self.blend_premul(dst.into(), src.into()).into(),
+        }
+    }
+
+    pub fn blend_premul(self, dst: PremulColor, src: PremulColor) -> PremulColor {
+        match self {
+            Self::Replace => return src,
+            Self::AlphaTest => return if src.a() != 0x00 { src } else { dst },
+            Self::SourceOver => match src.a() {
+                0x00 => return dst,
+                0xFF => return src,
+                _ => {}
+            },
+            _ => {}
+        }
+
+        let (sa, da) = (src.a() as u32, dst.a() as u32);
+        let a = match self {
+            Self::Additive => (sa + da).min(255),
+            Self::Subtract => da,
+            _ => sa + mul(da, 255 - sa),
+        };
+        let channel = |sc: u8, dc: u8| {
+            let (sc, dc) = (sc as u32, dc as u32);
+            let c = match self {
+                Self::Additive => sc + dc,
+                Self::Multiply => mul(sc, dc) + mul(sc, 255 - da) + mul(dc, 255 - sa),
+                Self::Screen => sc + dc - mul(sc, dc),
+                Self::Subtract => dc.saturating_sub(sc),
+                _ => sc + mul(dc, 255 - sa),
+            };
+            c.min(a) as u8
+        };
+        PremulColor::from_rgba(
+            channel(src.r(), dst.r()),
+            channel(src.g(), dst.g()),
+            channel(src.b(), dst.b()),
+            a as u8,
+        )
+    }
+
+    pub fn blend_linear(self, dst: Color, src: Color) -> Color {
+        match self {
+            Self::Replace | Self::AlphaTest => return self.blend(dst, src),
+            Self::SourceOver if src.a() == 0x00 => return dst,
+            Self::SourceOver if src.a() == 0xFF => return src,
+            _ => {}
+        }
+
+        let s = srgb::premultiply(src.to_linear());
+        let d = srgb::premultiply(dst.to_linear());
+        let (sa, da) = (s[3], d[3]);
+        let a = match self {
+            Self::Additive => (sa + da).min(1.0),
+            Self::Subtract => da,
+            _ => sa + da * (1.0 - sa),
+        };
+        let channel = |sc: f32, dc: f32| {
+            let c = match self {
+                Self::Additive => sc + dc,
+                Self::Multiply => sc * dc + sc * (1.0 - da) + dc * (1.0 - sa),
+                Self::Screen => sc + dc - sc * dc,
+                Self::Subtract => (dc - sc).max(0.0),
+                _ => sc + dc * (1.0 - sa),
+            };
+            c.min(a)
+        };
+        let out = [
+            channel(s[0], d[0]),
+            channel(s[1], d[1]),
+            channel(s[2], d[2]),
+            a,
+        ];
+        Color::from_linear(srgb::unpremultiply(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    const MODES: [BlendMode; 7] = [
+        BlendMode::Replace,
+        BlendMode::AlphaTest,
+        BlendMode::SourceOver,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Subtract,
+    ];
+
+    // Blends packed ARGB `src` over `dst` with every mode, in `MODES` order.
+    fn check(dst: u32, src: u32, expected: [u32; 7]) {
+        for (mode, expected) in MODES.into_iter().zip(expected) {
+            let result = mode.blend(Color::from_u32(dst), Color::from_u32(src));
+            assert_eq!(result.as_u32(), expected, "{mode:?} {dst:08X} {src:08X}");
+        }
+    }
+
+    #[test]
+    fn opaque_over_opaque() {
+        check(
+            0xFF804020,
+            0xFF40C010,
+            [
+                0xFF40C010, 0xFF40C010, 0xFF40C010, 0xFFC0FF30, 0xFF203002, 0xFFA0D02E, 0xFF400010,
+            ],
+        );
+    }
+
+    #[test]
+    fn transparent_source() {
+        // Everything but Replace leaves the destination alone.
+        let dst = 0xFF804020;
+        check(dst, 0x00FFFFFF, [0x00FFFFFF, dst, dst, dst, dst, dst, dst]);
+    }
+
+    #[test]
+    fn half_alpha_source() {
+        check(
+            0xFF000000,
+            0x80FFFFFF,
+            [
+                0x80FFFFFF, 0x80FFFFFF, 0xFF808080, 0xFF808080, 0xFF000000, 0xFF808080, 0xFF000000,
+            ],
+        );
+        check(
+            0xFFFFFFFF,
+            0x80FFFFFF,
+            [
+                0x80FFFFFF, 0x80FFFFFF, 0xFFFFFFFF, 0xFFFFFFFF, 0xFFFFFFFF, 0xFFFFFFFF, 0xFF7F7F7F,
+            ],
+        );
+    }
+
+    #[test]
+    fn translucent_destination() {
+        check(
+            0x00000000,
+            0x80FFFFFF,
+            [
+                0x80FFFFFF, 0x80FFFFFF, 0x80FFFFFF, 0x80FFFFFF, 0x80FFFFFF, 0x80FFFFFF, 0x00000000,
+            ],
+        );
+        check(
+            0x80FF0000,
+            0xFF0000FF,
+            [
+                0xFF0000FF, 0xFF0000FF, 0xFF0000FF, 0xFF8000FF, 0xFF00007F, 0xFF8000FF, 0x80FF0000,
+            ],
+        );
+    }
+
+    #[test]
+    fn default_blit_matches_alpha_test() {
+        assert_eq!(BlendMode::default(), BlendMode::AlphaTest);
+        assert_eq!(BlitOptions::default().blend, BlendMode::AlphaTest);
+        // Blits used to copy every pixel that wasn't fully transparent as is,
+        // without blending.
+        let mut src = Image::new(3, 1, Color::TRANSPARENT);
+        src.set_pixel(ivec2(1, 0), Color::from_u32(0x80FF0000));
+        src.set_pixel(ivec2(2, 0), Color::from_u32(0xFF00FF00));
+        let mut dst = Image::new(3, 1, Color::BLUE);
+        dst.blit(&src, None, None, None, None);
+        assert_eq!(
+            dst.pixels(),
+            Some(&[0xFF0000FF, 0x80FF0000, 0xFF00FF00][..])
+        );
+
+        let mut dst = Image::new(6, 1, Color::BLUE);
+        dst.blit(&src, None, None, None, Some(ivec2(6, 1)));
+        assert_eq!(
+            dst.pixels(),
+            Some(
+                &[
+                    0xFF0000FF, 0xFF0000FF, 0x80FF0000, 0x80FF0000, 0xFF00FF00, 0xFF00FF00
+                ][..]
+            )
+        );
+    }
+}
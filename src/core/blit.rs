@@ -1,68 +1,766 @@
 use crate::core::*;
 
-pub fn blit_same_size<A, B>(dst: &mut A, src: &B, src_pos: IVec2, dst_pos: IVec2, size: IVec2)
+use super::filter::sample;
+use super::simd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlitOptions {
+    pub blend: BlendMode,
+    pub linear: bool,
+    pub tint: Option<Color>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub filter: Filter,
+}
+
+fn is_linear(options: BlitOptions) -> bool {
+    options.linear && !matches!(options.blend, BlendMode::Replace | BlendMode::AlphaTest)
+}
+
+// Same as `copy_pixel` for non-linear blits, but on the raw values of surfaces
+// that expose their rows.
+fn copy_raw(dst: &mut u32, src: u32, src_premul: bool, dst_premul: bool, options: BlitOptions) {
+    let blend = options.blend;
+    if blend == BlendMode::Replace && options.tint.is_none() && src_premul == dst_premul {
+        *dst = src;
+    } else if src_premul || dst_premul {
+        let mut color = if src_premul {
+            PremulColor::from_u32(src)
+        } else {
+            PremulColor::from_color(Color::from_u32(src))
+        };
+        if let Some(tint) = options.tint {
+            color = color.modulate(tint.into());
+        }
+        if blend == BlendMode::AlphaTest && color.a() == 0x00 {
+            return;
+        }
+        if dst_premul {
+            *dst = blend
+                .blend_premul(PremulColor::from_u32(*dst), color)
+                .as_u32();
+        } else {
+            let dst_color = PremulColor::from_color(Color::from_u32(*dst));
+            *dst = blend.blend_premul(dst_color, color).to_color().as_u32();
+        }
+    } else {
+        let color = Color::from_u32(src);
+        let color = options.tint.map_or(color, |tint| color.modulate(tint));
+        *dst = blend.blend(Color::from_u32(*dst), color).as_u32();
+    }
+}
+
+// `copy_raw` over a whole span, using the SIMD kernels for plain copies,
+// tints and blending between premultiplied surfaces.
+fn copy_span(
+    dst: &mut [u32],
+    src: &[u32],
+    src_premul: bool,
+    dst_premul: bool,
+    options: BlitOptions,
+) {
+    let same_format = src_premul == dst_premul;
+    let tint = options.tint.map(|tint| {
+        if dst_premul {
+            PremulColor::from_color(tint).as_u32()
+        } else {
+            tint.as_u32()
+        }
+    });
+    match (options.blend, tint) {
+        (BlendMode::Replace, None) if same_format => simd::copy(dst, src),
+        (BlendMode::Replace, Some(tint)) if same_format => simd::tint(dst, src, tint),
+        (BlendMode::SourceOver, tint) if src_premul && dst_premul => {
+            let mut tinted = [0; 64];
+            for (dst, src) in dst.chunks_mut(tinted.len()).zip(src.chunks(tinted.len())) {
+                let src = match tint {
+                    Some(tint) => {
+                        let tinted = &mut tinted[..src.len()];
+                        simd::tint(tinted, src, tint);
+                        tinted
+                    }
+                    None => src,
+                };
+                simd::blend_over(dst, src);
+            }
+        }
+        _ => {
+            for (dst, src) in dst.iter_mut().zip(src) {
+                copy_raw(dst, *src, src_premul, dst_premul, options);
+            }
+        }
+    }
+}
+
+fn put_color<A>(dst: &mut A, dst_pos: IVec2, color: Color, options: BlitOptions)
 where
     A: Surface,
+{
+    let blend = options.blend;
+    let color = options.tint.map_or(color, |tint| color.modulate(tint));
+    if options.linear && !matches!(blend, BlendMode::Replace | BlendMode::AlphaTest) {
+        let dst_color = dst.get_pixel(dst_pos);
+        dst.set_pixel(dst_pos, blend.blend_linear(dst_color, color));
+    } else {
+        dst.blend_pixel(dst_pos, color, blend);
+    }
+}
+
+fn copy_pixel<A, B>(
+    dst: &mut A,
+    src: &B,
+    src_pos: IVec2,
+    dst_pos: IVec2,
+    options: BlitOptions,
+    premul: bool,
+) where
+    A: Surface,
     B: Surface,
 {
-    let dst_size = dst.size();
-    let src_size = src.size();
-    for y in 0..size.y {
-        let dst_offset_y = dst_pos.y + y;
-        if dst_offset_y < 0 || dst_offset_y >= dst_size.y {
+    if premul && !is_linear(options) {
+        let mut src_color = src.get_pixel_premul(src_pos);
+        if let Some(tint) = options.tint {
+            src_color = src_color.modulate(tint.into());
+        }
+        dst.blend_pixel_premul(dst_pos, src_color, options.blend);
+    } else {
+        put_color(dst, dst_pos, src.get_pixel(src_pos), options);
+    }
+}
+
+// Normalizes negative sizes into mirroring and dispatches to the same-size or
+// scaling blit.
+pub fn blit<A, B>(dst: &mut A, src: &B, src_rect: Rect, dst_rect: Rect, options: BlitOptions)
+where
+    A: Surface,
+    B: Surface,
+{
+    let mut options = options;
+    options.flip_x ^= (src_rect.size.x < 0) != (dst_rect.size.x < 0);
+    options.flip_y ^= (src_rect.size.y < 0) != (dst_rect.size.y < 0);
+    let (src_rect, dst_rect) = (src_rect.abs(), dst_rect.abs());
+    if src_rect.is_empty() || dst_rect.is_empty() {
+        return;
+    }
+    if src_rect.size == dst_rect.size {
+        blit_same_size(dst, src, src_rect, dst_rect.pos, options);
+    } else {
+        blit_scale(dst, src, src_rect, dst_rect, options);
+    }
+}
+
+fn mirror(i: i32, len: i32, flip: bool) -> i32 {
+    if flip { len - 1 - i } else { i }
+}
+
+fn blit_same_size<A, B>(dst: &mut A, src: &B, src_rect: Rect, dst_pos: IVec2, options: BlitOptions)
+where
+    A: Surface,
+    B: Surface,
+{
+    let size = src_rect.size;
+    // Source pixels that exist, relative to the region and in destination
+    // order, so that the loops below need no bounds checks.
+    let mut valid = src_rect
+        .intersect(Rect::from_size(src.size()))
+        .translate(-src_rect.pos);
+    if options.flip_x {
+        valid.pos.x = size.x - valid.max().x;
+    }
+    if options.flip_y {
+        valid.pos.y = size.y - valid.max().y;
+    }
+    let visible = valid.translate(dst_pos).intersect(dst.clip_rect());
+    if visible.is_empty() {
+        return;
+    }
+    let (src_premul, dst_premul) = (src.is_premultiplied(), dst.is_premultiplied());
+    let premul = src_premul || dst_premul;
+    let (x0, x1) = (visible.min().x, visible.max().x);
+    // First source column of the span and the direction it is read in.
+    let src_x0 = src_rect.pos.x + mirror(x0 - dst_pos.x, size.x, options.flip_x);
+    for y in visible.min().y..visible.max().y {
+        let src_y = src_rect.pos.y + mirror(y - dst_pos.y, size.y, options.flip_y);
+        if !is_linear(options)
+            && let Some(src_row) = src.row(src_y)
+            && let Some(dst_row) = dst.row_mut(y)
+        {
+            let len = (x1 - x0) as usize;
+            let dst_span = &mut dst_row[x0 as usize..x1 as usize];
+            if options.flip_x {
+                let src_span = &src_row[src_x0 as usize + 1 - len..=src_x0 as usize];
+                for (dst, src) in dst_span.iter_mut().zip(src_span.iter().rev()) {
+                    copy_raw(dst, *src, src_premul, dst_premul, options);
+                }
+            } else {
+                let src_span = &src_row[src_x0 as usize..src_x0 as usize + len];
+                copy_span(dst_span, src_span, src_premul, dst_premul, options);
+            }
             continue;
         }
-        let src_offset_y = src_pos.y + y;
-        if src_offset_y < 0 || src_offset_y >= src_size.y {
+        for x in x0..x1 {
+            let src_x = src_rect.pos.x + mirror(x - dst_pos.x, size.x, options.flip_x);
+            copy_pixel(dst, src, ivec2(src_x, src_y), ivec2(x, y), options, premul);
+        }
+    }
+}
+
+fn blit_scale<A, B>(dst: &mut A, src: &B, src_rect: Rect, dst_rect: Rect, options: BlitOptions)
+where
+    A: Surface,
+    B: Surface,
+{
+    let visible = dst_rect.intersect(dst.clip_rect());
+    let src_clip = src_rect.intersect(Rect::from_size(src.size()));
+    if visible.is_empty() || src_clip.is_empty() {
+        return;
+    }
+    let (src_premul, dst_premul) = (src.is_premultiplied(), dst.is_premultiplied());
+    let premul = src_premul || dst_premul;
+    let step = src_rect.size.as_vec2() / dst_rect.size.as_vec2();
+    // Destination column, sample index and source column of every visible
+    // column whose source pixel exists.
+    let columns: Vec<_> = (visible.min().x..visible.max().x)
+        .filter_map(|x| {
+            let i = mirror(x - dst_rect.pos.x, dst_rect.size.x, options.flip_x);
+            let src_x = src_rect.pos.x + (i as f32 * step.x) as i32;
+            (src_clip.min().x..src_clip.max().x)
+                .contains(&src_x)
+                .then_some((x, i, src_x))
+        })
+        .collect();
+    for y in visible.min().y..visible.max().y {
+        let j = mirror(y - dst_rect.pos.y, dst_rect.size.y, options.flip_y);
+        let src_y = src_rect.pos.y + (j as f32 * step.y) as i32;
+        if !(src_clip.min().y..src_clip.max().y).contains(&src_y) {
             continue;
         }
-        for x in 0..size.x {
-            let dst_offset_x = dst_pos.x + x;
-            if dst_offset_x < 0 || dst_offset_x >= dst_size.x {
-                continue;
+        if options.filter == Filter::Nearest
+            && !is_linear(options)
+            && let Some(src_row) = src.row(src_y)
+            && let Some(dst_row) = dst.row_mut(y)
+        {
+            for &(x, _, src_x) in &columns {
+                let src = src_row[src_x as usize];
+                copy_raw(
+                    &mut dst_row[x as usize],
+                    src,
+                    src_premul,
+                    dst_premul,
+                    options,
+                );
             }
-            let src_offset_x = src_pos.x + x;
-            if src_offset_x < 0 || src_offset_x >= src_size.x {
-                continue;
-            }
-            let src_color = src.get_pixel(ivec2(src_offset_x, src_offset_y));
-            if src_color.a() != 0x00 {
-                dst.set_pixel(ivec2(dst_offset_x, dst_offset_y), src_color);
+            continue;
+        }
+        for &(x, i, src_x) in &columns {
+            if options.filter == Filter::Nearest {
+                copy_pixel(dst, src, ivec2(src_x, src_y), ivec2(x, y), options, premul);
+            } else {
+                let pos = src_rect.pos.as_vec2() + (ivec2(i, j).as_vec2() + 0.5) * step;
+                let color = sample(
+                    src,
+                    options.filter,
+                    options.linear,
+                    pos,
+                    step,
+                    src_clip.min(),
+                    src_clip.max(),
+                );
+                put_color(dst, ivec2(x, y), color, options);
             }
         }
     }
 }
 
-pub fn blit_scale<A, B>(
+pub fn blit_transform<A, B>(
     dst: &mut A,
     src: &B,
-    src_pos: IVec2,
-    src_size: IVec2,
-    dst_pos: IVec2,
-    dst_size: IVec2,
+    src_rect: Rect,
+    transform: Affine2,
+    options: BlitOptions,
 ) where
     A: Surface,
     B: Surface,
 {
-    let base_size = dst.size();
-    let step_x = src_size.x as f32 / dst_size.x as f32;
-    let step_y = src_size.y as f32 / dst_size.y as f32;
-    for y in 0..dst_size.y {
-        let dst_offset_y = dst_pos.y + y;
-        if dst_offset_y < 0 || dst_offset_y >= base_size.y {
-            continue;
-        }
-        let src_offset_y = src_pos.y + (y as f32 * step_y) as i32;
-        for x in 0..dst_size.x {
-            let dst_offset_x = dst_pos.x + x;
-            if dst_offset_x < 0 || dst_offset_x >= base_size.x {
+    let mut options = options;
+    options.flip_x ^= src_rect.size.x < 0;
+    options.flip_y ^= src_rect.size.y < 0;
+    let src_rect = src_rect.abs();
+    // Only sample pixels that exist in both the requested region and `src`.
+    let src_clip = src_rect.intersect(Rect::from_size(src.size()));
+    let inverse = transform.inverse();
+    if src_clip.is_empty() || !inverse.is_finite() {
+        return;
+    }
+    let size = src_rect.size.as_vec2();
+    let corners = [vec2(0.0, 0.0), vec2(size.x, 0.0), vec2(0.0, size.y), size]
+        .map(|corner| transform.transform_point2(corner));
+    let (lo, hi) = corners
+        .iter()
+        .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(*p), hi.max(*p))
+        });
+    // Clip while still in floats, since the corners of huge transforms lie
+    // far outside the i32 range.
+    let clip = dst.clip_rect();
+    let lo = lo.floor().max(clip.min().as_vec2());
+    let hi = hi.ceil().min(clip.max().as_vec2());
+    if !lo.cmplt(hi).all() {
+        return;
+    }
+    let visible = Rect::from_min_max(lo.as_ivec2(), hi.as_ivec2());
+    let valid = src_clip.translate(-src_rect.pos);
+    let (min, max) = (valid.min().as_vec2(), valid.max().as_vec2());
+    let scale = vec2(
+        inverse.matrix2.x_axis.length(),
+        inverse.matrix2.y_axis.length(),
+    );
+    for y in visible.min().y..visible.max().y {
+        for x in visible.min().x..visible.max().x {
+            let mut local = inverse.transform_point2(vec2(x as f32 + 0.5, y as f32 + 0.5));
+            if options.flip_x {
+                local.x = size.x - local.x;
+            }
+            if options.flip_y {
+                local.y = size.y - local.y;
+            }
+            if local.cmplt(min).any() || local.cmpge(max).any() {
                 continue;
             }
-            let src_offset_x = src_pos.x + (x as f32 * step_x) as i32;
-            let src_color = src.get_pixel(ivec2(src_offset_x, src_offset_y));
-            if src_color.a() != 0x00 {
-                dst.set_pixel(ivec2(dst_offset_x, dst_offset_y), src_color);
+            let pos = src_rect.pos.as_vec2() + local;
+            let color = sample(
+                src,
+                options.filter,
+                options.linear,
+                pos,
+                scale,
+                src_clip.min(),
+                src_clip.max(),
+            );
+            put_color(dst, ivec2(x, y), color, options);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat2;
+
+    use crate::core::*;
+    use crate::image::Image;
+
+    const BACKGROUND: Color = Color::BLACK;
+
+    fn source() -> Image {
+        let mut image = Image::new(4, 4, Color::WHITE);
+        for y in 0..4 {
+            for x in 0..4 {
+                let color = Color::from_rgb(0x40 + x as u8 * 0x30, 0x40 + y as u8 * 0x30, 0x80);
+                image.set_pixel(ivec2(x, y), color);
+            }
+        }
+        image
+    }
+
+    fn blit(
+        src_pos: IVec2,
+        src_size: IVec2,
+        dst_pos: IVec2,
+        dst_size: Option<IVec2>,
+        options: BlitOptions,
+    ) -> Image {
+        let mut dst = Image::new(8, 8, BACKGROUND);
+        dst.blit_with(
+            &source(),
+            Some(src_pos),
+            Some(src_size),
+            Some(dst_pos),
+            dst_size,
+            options,
+        );
+        dst
+    }
+
+    // The expected destination, given the source pixel each destination pixel
+    // should show.
+    fn expected<F>(map: F) -> Image
+    where
+        F: Fn(IVec2) -> Option<IVec2>,
+    {
+        let src = source();
+        let mut dst = Image::new(8, 8, BACKGROUND);
+        for y in 0..8 {
+            for x in 0..8 {
+                let pos = ivec2(x, y);
+                if let Some(src_pos) = map(pos)
+                    && Rect::from_size(src.size()).contains(src_pos)
+                {
+                    dst.set_pixel(pos, src.get_pixel(src_pos));
+                }
+            }
+        }
+        dst
+    }
+
+    fn within(rect: Rect, pos: IVec2) -> Option<IVec2> {
+        rect.contains(pos).then_some(pos - rect.pos)
+    }
+
+    fn assert_same(a: &Image, b: &Image) {
+        assert_eq!(a.pixels(), b.pixels());
+    }
+
+    fn untouched() -> Image {
+        Image::new(8, 8, BACKGROUND)
+    }
+
+    #[test]
+    fn source_partly_off_image() {
+        let options = BlitOptions::default();
+        let dst = blit(ivec2(-2, -1), ivec2(4, 4), ivec2(1, 1), None, options);
+        let rect = Rect::new(ivec2(1, 1), ivec2(4, 4));
+        assert_same(&dst, &expected(|p| Some(within(rect, p)? + ivec2(-2, -1))));
+
+        let dst = blit(ivec2(2, 3), ivec2(4, 4), ivec2(0, 0), None, options);
+        let rect = Rect::from_size(ivec2(4, 4));
+        assert_same(&dst, &expected(|p| Some(within(rect, p)? + ivec2(2, 3))));
+    }
+
+    #[test]
+    fn scaled_source_partly_off_image() {
+        let options = BlitOptions::default();
+        let dst = blit(
+            ivec2(2, 2),
+            ivec2(4, 4),
+            IVec2::ZERO,
+            Some(ivec2(8, 8)),
+            options,
+        );
+        assert_same(&dst, &expected(|p| Some(ivec2(2, 2) + p / 2)));
+
+        let dst = blit(
+            ivec2(-2, 0),
+            ivec2(4, 4),
+            IVec2::ZERO,
+            Some(ivec2(8, 8)),
+            options,
+        );
+        assert_same(&dst, &expected(|p| Some(ivec2(-2, 0) + p / 2)));
+    }
+
+    #[test]
+    fn destination_partly_off_surface() {
+        let options = BlitOptions::default();
+        let dst = blit(IVec2::ZERO, ivec2(4, 4), ivec2(-2, 6), None, options);
+        let rect = Rect::new(ivec2(-2, 6), ivec2(4, 4));
+        assert_same(&dst, &expected(|p| within(rect, p)));
+
+        let dst = blit(
+            IVec2::ZERO,
+            ivec2(4, 4),
+            ivec2(6, -3),
+            Some(ivec2(8, 8)),
+            options,
+        );
+        let rect = Rect::new(ivec2(6, -3), ivec2(8, 8));
+        assert_same(&dst, &expected(|p| Some(within(rect, p)? / 2)));
+    }
+
+    #[test]
+    fn negative_destination_size_mirrors() {
+        let options = BlitOptions::default();
+        let dst = blit(
+            IVec2::ZERO,
+            ivec2(4, 4),
+            ivec2(4, 0),
+            Some(ivec2(-4, 4)),
+            options,
+        );
+        let rect = Rect::from_size(ivec2(4, 4));
+        let mirrored = expected(|p| within(rect, p).map(|p| ivec2(3 - p.x, p.y)));
+        assert_same(&dst, &mirrored);
+
+        let flip_x = BlitOptions {
+            flip_x: true,
+            ..options
+        };
+        assert_same(
+            &blit(IVec2::ZERO, ivec2(4, 4), IVec2::ZERO, None, flip_x),
+            &mirrored,
+        );
+
+        let dst = blit(
+            IVec2::ZERO,
+            ivec2(4, 4),
+            ivec2(8, 8),
+            Some(ivec2(-8, -8)),
+            options,
+        );
+        assert_same(&dst, &expected(|p| Some(ivec2(3, 3) - p / 2)));
+    }
+
+    #[test]
+    fn negative_source_width_flips() {
+        let options = BlitOptions::default();
+        let dst = blit(ivec2(4, 0), ivec2(-4, 4), IVec2::ZERO, None, options);
+        let rect = Rect::from_size(ivec2(4, 4));
+        assert_same(
+            &dst,
+            &expected(|p| within(rect, p).map(|p| ivec2(3 - p.x, p.y))),
+        );
+
+        let dst = blit(ivec2(4, 4), ivec2(-4, -4), IVec2::ZERO, None, options);
+        assert_same(
+            &dst,
+            &expected(|p| within(rect, p).map(|p| ivec2(3, 3) - p)),
+        );
+
+        let dst = blit(
+            ivec2(4, 0),
+            ivec2(-4, 4),
+            IVec2::ZERO,
+            Some(ivec2(8, 8)),
+            options,
+        );
+        assert_same(&dst, &expected(|p| Some(ivec2(3 - p.x / 2, p.y / 2))));
+
+        // The region reaches back from the position, so this one is entirely
+        // left of the source.
+        let dst = blit(IVec2::ZERO, ivec2(-4, 4), IVec2::ZERO, None, options);
+        assert_same(&dst, &untouched());
+    }
+
+    #[test]
+    fn negative_sizes_cancel_out() {
+        let options = BlitOptions::default();
+        let dst = blit(
+            ivec2(4, 0),
+            ivec2(-4, 4),
+            ivec2(4, 0),
+            Some(ivec2(-4, 4)),
+            options,
+        );
+        let rect = Rect::from_size(ivec2(4, 4));
+        assert_same(&dst, &expected(|p| within(rect, p)));
+    }
+
+    #[test]
+    fn flipped_and_clipped() {
+        let options = BlitOptions {
+            flip_x: true,
+            flip_y: true,
+            ..Default::default()
+        };
+        let dst = blit(IVec2::ZERO, ivec2(4, 4), ivec2(-2, 5), None, options);
+        let rect = Rect::new(ivec2(-2, 5), ivec2(4, 4));
+        assert_same(
+            &dst,
+            &expected(|p| within(rect, p).map(|p| ivec2(3, 3) - p)),
+        );
+    }
+
+    #[test]
+    fn zero_sizes() {
+        let options = BlitOptions::default();
+        for (src_size, dst_size) in [
+            (ivec2(0, 4), None),
+            (ivec2(4, 0), None),
+            (ivec2(4, 4), Some(ivec2(0, 4))),
+            (ivec2(4, 4), Some(ivec2(4, 0))),
+            (ivec2(4, 4), Some(IVec2::ZERO)),
+        ] {
+            let dst = blit(IVec2::ZERO, src_size, IVec2::ZERO, dst_size, options);
+            assert_same(&dst, &untouched());
+        }
+    }
+
+    #[test]
+    fn empty_intersections() {
+        let options = BlitOptions::default();
+        for dst_pos in [
+            ivec2(8, 0),
+            ivec2(0, 8),
+            ivec2(-4, 0),
+            ivec2(0, -4),
+            ivec2(100, -100),
+        ] {
+            let dst = blit(IVec2::ZERO, ivec2(4, 4), dst_pos, None, options);
+            assert_same(&dst, &untouched());
+            let dst = blit(
+                IVec2::ZERO,
+                ivec2(4, 4),
+                dst_pos * 2,
+                Some(ivec2(8, 8)),
+                options,
+            );
+            assert_same(&dst, &untouched());
+        }
+        for src_pos in [ivec2(4, 0), ivec2(0, 4), ivec2(-4, -4), ivec2(-10, 2)] {
+            let dst = blit(src_pos, ivec2(4, 4), IVec2::ZERO, None, options);
+            assert_same(&dst, &untouched());
+            let dst = blit(
+                src_pos,
+                ivec2(4, 4),
+                IVec2::ZERO,
+                Some(ivec2(8, 8)),
+                options,
+            );
+            assert_same(&dst, &untouched());
+        }
+    }
+
+    #[test]
+    fn premultiplied_spans_match_per_pixel() {
+        // Wide enough that the spans use whole vectors and a remainder.
+        let (w, h) = (37, 5);
+        let color = |x: i32, y: i32, seed: i32| {
+            let v = (x * 53 + y * 97 + seed) as u8;
+            Color::from_rgba(v, v.wrapping_mul(3), v ^ 0x5A, v.wrapping_mul(7))
+        };
+        let image = |seed| {
+            let mut image = Image::new(w, h, Color::TRANSPARENT);
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    image.set_pixel(ivec2(x, y), color(x, y, seed));
+                }
+            }
+            image.premultiply();
+            image
+        };
+        let src = image(1);
+        let tint = Color::from_rgba(0xC0, 0x80, 0xFF, 0xA0);
+        for (blend, tint) in [
+            (BlendMode::Replace, None),
+            (BlendMode::Replace, Some(tint)),
+            (BlendMode::SourceOver, None),
+            (BlendMode::SourceOver, Some(tint)),
+        ] {
+            let options = BlitOptions {
+                blend,
+                tint,
+                ..Default::default()
+            };
+            let mut expected = image(2);
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    let pos = ivec2(x, y);
+                    let mut color = src.get_pixel_premul(pos);
+                    if let Some(tint) = tint {
+                        color = color.modulate(tint.into());
+                    }
+                    let color = blend.blend_premul(expected.get_pixel_premul(pos), color);
+                    expected.set_pixel_premul(pos, color);
+                }
+            }
+            let mut dst = image(2);
+            dst.blit_with(&src, None, None, None, None, options);
+            assert_eq!(dst.pixels(), expected.pixels(), "{blend:?} {tint:?}");
+        }
+    }
+
+    fn blit_affine(transform: Affine2, filter: Filter, clip: Option<Rect>) -> Image {
+        let mut dst = untouched();
+        if let Some(clip) = clip {
+            dst.push_clip(clip);
+        }
+        let options = BlitOptions {
+            filter,
+            ..Default::default()
+        };
+        dst.blit_transform(&source(), None, None, transform, options);
+        dst
+    }
+
+    // A quarter turn, exact in f32.
+    fn quarter_turn(offset: Vec2) -> Affine2 {
+        Affine2::from_mat2_translation(Mat2::from_cols(vec2(0.0, 1.0), vec2(-1.0, 0.0)), offset)
+    }
+
+    const FILTERS: [Filter; 2] = [Filter::Nearest, Filter::Bilinear];
+
+    #[test]
+    fn affine_translation() {
+        let rect = Rect::new(ivec2(3, 2), ivec2(4, 4));
+        for filter in FILTERS {
+            let dst = blit_affine(Affine2::from_translation(vec2(3.0, 2.0)), filter, None);
+            assert_same(&dst, &expected(|p| within(rect, p)));
+        }
+    }
+
+    #[test]
+    fn affine_rotation() {
+        // Source pixel (u, v) lands on (5 - v, 1 + u).
+        let rect = Rect::new(ivec2(2, 1), ivec2(4, 4));
+        for filter in FILTERS {
+            let dst = blit_affine(quarter_turn(vec2(6.0, 1.0)), filter, None);
+            let map = |p| within(rect, p).map(|p: IVec2| ivec2(p.y, 3 - p.x));
+            assert_same(&dst, &expected(map));
+        }
+    }
+
+    #[test]
+    fn affine_scale_matches_scaled_blit() {
+        for filter in FILTERS {
+            let dst = blit_affine(Affine2::from_scale(vec2(2.0, 2.0)), filter, None);
+            let mut scaled = untouched();
+            let options = BlitOptions {
+                filter,
+                ..Default::default()
+            };
+            scaled.blit_with(&source(), None, None, None, Some(ivec2(8, 8)), options);
+            assert_same(&dst, &scaled);
+        }
+    }
+
+    #[test]
+    fn affine_clipped() {
+        let clip = Rect::new(ivec2(3, 2), ivec2(3, 5));
+        for filter in FILTERS {
+            for transform in [
+                quarter_turn(vec2(6.0, 1.0)),
+                Affine2::from_scale_angle_translation(vec2(1.5, 2.5), 0.3, vec2(2.0, -1.0)),
+                Affine2::from_translation(vec2(-2.0, -1.0)),
+            ] {
+                let full = blit_affine(transform, filter, None);
+                let dst = blit_affine(transform, filter, Some(clip));
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let pos = ivec2(x, y);
+                        let expected = if clip.contains(pos) {
+                            full.get_pixel(pos)
+                        } else {
+                            BACKGROUND
+                        };
+                        assert_eq!(dst.get_pixel(pos), expected, "{pos}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn affine_singular_and_huge() {
+        for filter in FILTERS {
+            for transform in [
+                Affine2::from_scale(vec2(0.0, 1.0)),
+                Affine2::from_scale(Vec2::ZERO),
+                Affine2::from_mat2(Mat2::from_cols(vec2(1.0, 2.0), vec2(2.0, 4.0))),
+                Affine2::from_translation(vec2(1e30, -1e30)),
+                Affine2::from_translation(vec2(f32::NAN, 0.0)),
+                Affine2::from_scale_angle_translation(vec2(1e-30, 1.0), 1.0, Vec2::ZERO),
+            ] {
+                assert_same(&blit_affine(transform, filter, None), &untouched());
             }
+            // Every destination pixel falls into the first source pixel.
+            let dst = blit_affine(Affine2::from_scale(vec2(1e20, 1e20)), filter, None);
+            let first = source().get_pixel(IVec2::ZERO);
+            assert!(dst.pixels().unwrap().iter().all(|&p| p == first.as_u32()));
+            // Corners on both sides of the i32 range.
+            let transform =
+                Affine2::from_scale_angle_translation(Vec2::splat(1e20), 2.0, vec2(4.0, 4.0));
+            let dst = blit_affine(transform, filter, None);
+            let allowed = [first.as_u32(), BACKGROUND.as_u32()];
+            assert!(dst.pixels().unwrap().iter().all(|p| allowed.contains(p)));
         }
     }
 }
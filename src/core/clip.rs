@@ -0,0 +1,97 @@
+use crate::core::*;
+
+// Nested clip rectangles. Every pushed rectangle is stored already intersected
+// with the one below it, so the current clip is simply the top of the stack.
+#[derive(Debug, Clone, Default)]
+pub struct ClipStack {
+    rects: Vec<Rect>,
+}
+
+impl ClipStack {
+    pub const fn new() -> Self {
+        Self { rects: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    // Narrows the clip to `rect`. `bounds` is the clip used while the stack is
+    // empty.
+    pub fn push(&mut self, rect: Rect, bounds: Rect) {
+        let clip = rect.abs().intersect(self.clip(bounds));
+        self.rects.push(clip);
+    }
+
+    pub fn pop(&mut self) -> Option<Rect> {
+        self.rects.pop()
+    }
+
+    pub fn top(&self) -> Option<Rect> {
+        self.rects.last().copied()
+    }
+
+    pub fn clip(&self, bounds: Rect) -> Rect {
+        self.top().unwrap_or(bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: Rect = Rect::new(IVec2::ZERO, ivec2(10, 10));
+
+    #[test]
+    fn nested_clips_intersect() {
+        let mut stack = ClipStack::new();
+        assert_eq!(stack.clip(BOUNDS), BOUNDS);
+        stack.push(Rect::new(ivec2(2, 2), ivec2(6, 6)), BOUNDS);
+        assert_eq!(stack.clip(BOUNDS), Rect::new(ivec2(2, 2), ivec2(6, 6)));
+        stack.push(Rect::new(ivec2(5, 0), ivec2(10, 4)), BOUNDS);
+        assert_eq!(stack.clip(BOUNDS), Rect::new(ivec2(5, 2), ivec2(3, 2)));
+        assert_eq!(stack.len(), 2);
+
+        assert_eq!(stack.pop(), Some(Rect::new(ivec2(5, 2), ivec2(3, 2))));
+        assert_eq!(stack.clip(BOUNDS), Rect::new(ivec2(2, 2), ivec2(6, 6)));
+        assert_eq!(stack.pop(), Some(Rect::new(ivec2(2, 2), ivec2(6, 6))));
+        assert_eq!(stack.clip(BOUNDS), BOUNDS);
+    }
+
+    #[test]
+    fn pop_on_empty_stack() {
+        let mut stack = ClipStack::new();
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+        assert_eq!(stack.top(), None);
+        assert_eq!(stack.clip(BOUNDS), BOUNDS);
+    }
+
+    #[test]
+    fn clips_stay_within_bounds() {
+        let mut stack = ClipStack::new();
+        stack.push(Rect::new(ivec2(-5, -5), ivec2(100, 8)), BOUNDS);
+        assert_eq!(stack.clip(BOUNDS), Rect::new(IVec2::ZERO, ivec2(10, 3)));
+        stack.pop();
+
+        // Negative sizes are flipped.
+        stack.push(Rect::new(ivec2(6, 6), ivec2(-4, -2)), BOUNDS);
+        assert_eq!(stack.clip(BOUNDS), Rect::new(ivec2(2, 4), ivec2(4, 2)));
+        stack.pop();
+
+        // Clips outside the bounds are empty, as is everything pushed on them.
+        stack.push(Rect::new(ivec2(20, 2), ivec2(4, 4)), BOUNDS);
+        assert!(stack.clip(BOUNDS).is_empty());
+        stack.push(BOUNDS, BOUNDS);
+        assert!(stack.clip(BOUNDS).is_empty());
+        stack.pop();
+        stack.pop();
+
+        stack.push(Rect::new(ivec2(2, 2), IVec2::ZERO), BOUNDS);
+        assert!(stack.clip(BOUNDS).is_empty());
+    }
+}
@@ -1,3 +1,26 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+use std::str::FromStr;
+
+use super::premul::mul;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseColorError {
+    InvalidLength,
+    InvalidDigit,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => f.write_str("hex color must have 3, 4, 6 or 8 digits"),
+            Self::InvalidDigit => f.write_str("invalid hex digit in color"),
+        }
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Color(u32);
 
@@ -22,6 +45,138 @@ impl Color {
         (self.0 >> 24) as u8
     }
 
+    pub const fn r(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    pub const fn g(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub const fn b(self) -> u8 {
+        self.0 as u8
+    }
+
+    pub const fn with_r(self, r: u8) -> Self {
+        Self((self.0 & 0xFF00FFFF) | ((r as u32) << 16))
+    }
+
+    pub const fn with_g(self, g: u8) -> Self {
+        Self((self.0 & 0xFFFF00FF) | ((g as u32) << 8))
+    }
+
+    pub const fn with_b(self, b: u8) -> Self {
+        Self((self.0 & 0xFFFFFF00) | (b as u32))
+    }
+
+    pub const fn with_a(self, a: u8) -> Self {
+        Self((self.0 & 0x00FFFFFF) | ((a as u32) << 24))
+    }
+
+    pub const fn to_rgba_array(self) -> [u8; 4] {
+        [self.r(), self.g(), self.b(), self.a()]
+    }
+
+    pub const fn from_rgba_array(rgba: [u8; 4]) -> Self {
+        Self::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3])
+    }
+
+    pub const fn to_f32_array(self) -> [f32; 4] {
+        [
+            self.r() as f32 / 255.0,
+            self.g() as f32 / 255.0,
+            self.b() as f32 / 255.0,
+            self.a() as f32 / 255.0,
+        ]
+    }
+
+    pub const fn from_f32_array(rgba: [f32; 4]) -> Self {
+        Self::from_rgba(
+            unit_to_u8(rgba[0]),
+            unit_to_u8(rgba[1]),
+            unit_to_u8(rgba[2]),
+            unit_to_u8(rgba[3]),
+        )
+    }
+
+    pub const fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        Self::from_rgba(
+            lerp_u8(self.r(), other.r(), t),
+            lerp_u8(self.g(), other.g(), t),
+            lerp_u8(self.b(), other.b(), t),
+            lerp_u8(self.a(), other.a(), t),
+        )
+    }
+
+    pub const fn scale(self, factor: f32) -> Self {
+        Self::from_rgba(
+            scale_u8(self.r(), factor),
+            scale_u8(self.g(), factor),
+            scale_u8(self.b(), factor),
+            self.a(),
+        )
+    }
+
+    pub const fn saturating_add(self, other: Self) -> Self {
+        Self::from_rgba(
+            self.r().saturating_add(other.r()),
+            self.g().saturating_add(other.g()),
+            self.b().saturating_add(other.b()),
+            self.a().saturating_add(other.a()),
+        )
+    }
+
+    pub const fn saturating_sub(self, other: Self) -> Self {
+        Self::from_rgba(
+            self.r().saturating_sub(other.r()),
+            self.g().saturating_sub(other.g()),
+            self.b().saturating_sub(other.b()),
+            self.a().saturating_sub(other.a()),
+        )
+    }
+
+    pub const fn modulate(self, other: Self) -> Self {
+        Self::from_rgba(
+            mul(self.r() as u32, other.r() as u32) as u8,
+            mul(self.g() as u32, other.g() as u32) as u8,
+            mul(self.b() as u32, other.b() as u32) as u8,
+            mul(self.a() as u32, other.a() as u32) as u8,
+        )
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, ParseColorError> {
+        let s = s.strip_prefix('#').unwrap_or(s);
+        let mut digits = [0u8; 8];
+        if !matches!(s.len(), 3 | 4 | 6 | 8) {
+            return Err(ParseColorError::InvalidLength);
+        }
+        for (digit, c) in digits.iter_mut().zip(s.bytes()) {
+            *digit = (c as char)
+                .to_digit(16)
+                .ok_or(ParseColorError::InvalidDigit)? as u8;
+        }
+        let short = |i: usize| digits[i] * 0x11;
+        let long = |i: usize| (digits[i * 2] << 4) | digits[i * 2 + 1];
+        Ok(match s.len() {
+            3 => Self::from_rgb(short(0), short(1), short(2)),
+            4 => Self::from_rgba(short(0), short(1), short(2), short(3)),
+            6 => Self::from_rgb(long(0), long(1), long(2)),
+            _ => Self::from_rgba(long(0), long(1), long(2), long(3)),
+        })
+    }
+
+    pub fn to_hex(self) -> String {
+        format!(
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.r(),
+            self.g(),
+            self.b(),
+            self.a()
+        )
+    }
+
+    pub const TRANSPARENT: Self = Self::from_rgba(0x00, 0x00, 0x00, 0x00);
     pub const BLACK: Self = Self::from_rgb(0x00, 0x00, 0x00);
     pub const WHITE: Self = Self::from_rgb(0xFF, 0xFF, 0xFF);
     pub const RED: Self = Self::from_rgb(0xFF, 0x00, 0x00);
@@ -41,3 +196,200 @@ impl Color {
     pub const PINK: Self = Self::from_rgb(0xFF, 0xC0, 0xCB);
     pub const PURPLE: Self = Self::from_rgb(0x80, 0x00, 0x80);
 }
+
+const fn unit_to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+const fn lerp_u8(a: u8, b: u8, t: f32) -> u8 {
+    (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8
+}
+
+const fn scale_u8(v: u8, factor: f32) -> u8 {
+    (v as f32 * factor + 0.5).clamp(0.0, 255.0) as u8
+}
+
+impl Add for Color {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.saturating_add(rhs)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.saturating_add(rhs);
+    }
+}
+
+impl Sub for Color {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl SubAssign for Color {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.saturating_sub(rhs);
+    }
+}
+
+impl Mul for Color {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.modulate(rhs)
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = self.modulate(rhs);
+    }
+}
+
+impl Mul<f32> for Color {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        self.scale(rhs)
+    }
+}
+
+impl MulAssign<f32> for Color {
+    fn mul_assign(&mut self, rhs: f32) {
+        *self = self.scale(rhs);
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Color({})", self.to_hex())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_layout() {
+        let color = Color::from_rgba(0x12, 0x34, 0x56, 0x78);
+        assert_eq!(color.as_u32(), 0x78123456);
+        assert_eq!(Color::from_u32(0x78123456), color);
+        assert_eq!(Color::from_rgb(0x12, 0x34, 0x56).as_u32(), 0xFF123456);
+        assert_eq!(Color::TRANSPARENT.as_u32(), 0);
+        assert_eq!(Color::RED.as_u32(), 0xFFFF0000);
+    }
+
+    #[test]
+    fn accessors() {
+        let color = Color::from_rgba(0x12, 0x34, 0x56, 0x78);
+        assert_eq!(
+            [color.r(), color.g(), color.b(), color.a()],
+            [0x12, 0x34, 0x56, 0x78]
+        );
+        assert_eq!(color.with_r(0xAB), Color::from_rgba(0xAB, 0x34, 0x56, 0x78));
+        assert_eq!(color.with_g(0xAB), Color::from_rgba(0x12, 0xAB, 0x56, 0x78));
+        assert_eq!(color.with_b(0xAB), Color::from_rgba(0x12, 0x34, 0xAB, 0x78));
+        assert_eq!(color.with_a(0xAB), Color::from_rgba(0x12, 0x34, 0x56, 0xAB));
+    }
+
+    #[test]
+    fn arrays() {
+        let color = Color::from_rgba(0x00, 0x33, 0x80, 0xFF);
+        assert_eq!(color.to_rgba_array(), [0x00, 0x33, 0x80, 0xFF]);
+        assert_eq!(Color::from_rgba_array(color.to_rgba_array()), color);
+        assert_eq!(Color::from_f32_array(color.to_f32_array()), color);
+        assert_eq!(Color::from_f32_array([-1.0, 0.2, 0.5, 2.0]), color);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Color::from_rgba(0xF0, 0x80, 0x10, 0x80);
+        let b = Color::from_rgba(0x20, 0x40, 0x20, 0xFF);
+        assert_eq!(a + b, Color::from_rgba(0xFF, 0xC0, 0x30, 0xFF));
+        assert_eq!(a - b, Color::from_rgba(0xD0, 0x40, 0x00, 0x00));
+        assert_eq!(a * Color::WHITE, a);
+        assert_eq!(a * Color::TRANSPARENT, Color::TRANSPARENT);
+        assert_eq!(a * b, Color::from_rgba(0x1E, 0x20, 0x02, 0x80));
+        assert_eq!(a * 0.5, Color::from_rgba(0x78, 0x40, 0x08, 0x80));
+        assert_eq!(b * 10.0, Color::from_rgba(0xFF, 0xFF, 0xFF, 0xFF));
+        let mut c = a;
+        c += b;
+        c -= b;
+        c *= Color::WHITE;
+        c *= 1.0;
+        // Saturation loses information, so this is not `a` again.
+        assert_eq!(c, Color::from_rgba(0xDF, 0x80, 0x10, 0x00));
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Color::from_rgba(0x00, 0x40, 0xFF, 0x00);
+        let b = Color::from_rgba(0xFF, 0x40, 0x00, 0xFF);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Color::from_rgba(0x80, 0x40, 0x80, 0x80));
+        assert_eq!(a.lerp(b, 2.0), b);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for v in (0..=255).step_by(5) {
+            let color = Color::from_rgba(v, 255 - v, v / 3, 255 - v / 2);
+            assert_eq!(Color::from_hex(&color.to_hex()), Ok(color));
+            assert_eq!(color.to_string().parse(), Ok(color));
+        }
+        assert_eq!(
+            Color::from_rgba(0x12, 0xAB, 0x05, 0xFF).to_hex(),
+            "#12AB05FF"
+        );
+    }
+
+    #[test]
+    fn hex_forms() {
+        assert_eq!("#1a2b3c".parse(), Ok(Color::from_rgb(0x1A, 0x2B, 0x3C)));
+        assert_eq!(
+            "1A2B3C80".parse(),
+            Ok(Color::from_rgba(0x1A, 0x2B, 0x3C, 0x80))
+        );
+        assert_eq!("#f80".parse(), Ok(Color::from_rgb(0xFF, 0x88, 0x00)));
+        assert_eq!("f808".parse(), Ok(Color::from_rgba(0xFF, 0x88, 0x00, 0x88)));
+    }
+
+    #[test]
+    fn hex_errors() {
+        for s in ["", "#", "#12", "#12345", "#1234567", "#123456789"] {
+            assert_eq!(
+                Color::from_hex(s),
+                Err(ParseColorError::InvalidLength),
+                "{s}"
+            );
+        }
+        for s in ["#12g", "#12345z", "+1234567"] {
+            assert_eq!(
+                Color::from_hex(s),
+                Err(ParseColorError::InvalidDigit),
+                "{s}"
+            );
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use glam::I64Vec2;
+
+use crate::core::*;
+
+use super::simd;
+
+fn put<S>(surface: &mut S, pos: IVec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    if surface.clip_rect().contains(pos) {
+        surface.blend_pixel(pos, color, BlendMode::SourceOver);
+    }
+}
+
+pub(crate) fn hline<S, P>(surface: &mut S, x0: i32, x1: i32, y: i32, paint: P)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    let clip = surface.clip_rect();
+    if y < clip.min().y || y >= clip.max().y {
+        return;
+    }
+    let (x0, x1) = (x0.max(clip.min().x), x1.min(clip.max().x - 1));
+    if x0 > x1 {
+        return;
+    }
+    // Opaque colors are stored the same way whether premultiplied or not.
+    if let Some(color) = paint.solid_color()
+        && color.a() == 0xFF
+        && let Some(row) = surface.row_mut(y)
+    {
+        simd::fill(&mut row[x0 as usize..=x1 as usize], color.as_u32());
+        return;
+    }
+    for x in x0..=x1 {
+        let pos = ivec2(x, y);
+        surface.blend_pixel(pos, paint.color_at(pos), BlendMode::SourceOver);
+    }
+}
+
+fn vline<S>(surface: &mut S, x: i32, y0: i32, y1: i32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let clip = surface.clip_rect();
+    if x < clip.min().x || x >= clip.max().x {
+        return;
+    }
+    for y in y0.max(clip.min().y)..=y1.min(clip.max().y - 1) {
+        surface.blend_pixel(ivec2(x, y), color, BlendMode::SourceOver);
+    }
+}
+
+// Offsets from `start` along an axis stepping by `sign` that land within
+// `min..max`.
+fn axis_range(start: i64, sign: i64, min: i32, max: i32) -> (i64, i64) {
+    let (min, max) = (min as i64 - start, max as i64 - 1 - start);
+    if sign > 0 { (min, max) } else { (-max, -min) }
+}
+
+// Bresenham line. Pixel `i` along the major axis is `round(i * minor / major)`
+// along the minor one, so the steps that fall inside the clip rectangle can be
+// found up front instead of walking the whole line.
+pub fn draw_line<S>(surface: &mut S, a: IVec2, b: IVec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let clip = surface.clip_rect();
+    if clip.is_empty() {
+        return;
+    }
+    let (min, max) = (clip.min(), clip.max());
+    let start = a.as_i64vec2();
+    let delta = b.as_i64vec2() - start;
+    let sign = delta.signum();
+    let (major, minor) = if delta.x.abs() >= delta.y.abs() {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    let (lo, hi) = axis_range(start[major], sign[major], min[major], max[major]);
+    let (first, last) = (lo.max(0), hi.min(delta[major].abs()));
+    let (lo, hi) = axis_range(start[minor], sign[minor], min[minor], max[minor]);
+
+    // Lines spanning the whole i32 range need more than 64 bits below.
+    let (mut first, mut last) = (first as i128, last as i128);
+    let (lo, hi) = (lo as i128, hi as i128);
+    let (major_len, minor_len) = (delta[major].abs() as i128, delta[minor].abs() as i128);
+    if minor_len == 0 {
+        if lo > 0 || hi < 0 {
+            return;
+        }
+    } else {
+        // Solve lo <= round(i * minor_len / major_len) <= hi for i.
+        let (num, den) = (2 * major_len, 2 * minor_len);
+        first = first.max(-(major_len - num * lo).div_euclid(den));
+        last = last.min((num * (hi + 1) - major_len - 1).div_euclid(den));
+    }
+    for i in first..=last {
+        let mut offset = I64Vec2::ZERO;
+        offset[major] = i as i64;
+        if major_len != 0 {
+            offset[minor] = (2 * i * minor_len + major_len).div_euclid(2 * major_len) as i64;
+        }
+        put(surface, (start + offset * sign).as_ivec2(), color);
+    }
+}
+
+// Fills a convex polygon by sampling pixel centers.
+pub(crate) fn fill_convex<S>(surface: &mut S, points: &[Vec2], color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let clip = surface.clip_rect();
+    let (min, max) = points
+        .iter()
+        .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), p| {
+            (min.min(*p), max.max(*p))
+        });
+    let y0 = ((min.y - 0.5).ceil() as i32).max(clip.min().y);
+    let y1 = ((max.y - 0.5).floor() as i32).min(clip.max().y - 1);
+    for y in y0..=y1 {
+        let cy = y as f32 + 0.5;
+        let mut left = f32::INFINITY;
+        let mut right = f32::NEG_INFINITY;
+        for i in 0..points.len() {
+            let p = points[i];
+            let q = points[(i + 1) % points.len()];
+            if (p.y <= cy && q.y > cy) || (q.y <= cy && p.y > cy) {
+                let x = p.x + (cy - p.y) / (q.y - p.y) * (q.x - p.x);
+                left = left.min(x);
+                right = right.max(x);
+            }
+        }
+        if left <= right {
+            let x0 = (left - 0.5).ceil() as i32;
+            let x1 = (right - 0.5).ceil() as i32 - 1;
+            hline(surface, x0, x1, y, color);
+        }
+    }
+}
+
+pub fn draw_thick_line<S>(surface: &mut S, a: IVec2, b: IVec2, width: i32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    if width <= 1 {
+        draw_line(surface, a, b, color);
+        return;
+    }
+    let a = a.as_vec2() + 0.5;
+    let b = b.as_vec2() + 0.5;
+    let dir = (b - a).normalize_or(Vec2::X);
+    let normal = dir.perp() * (width as f32 / 2.0);
+    // Extend by half a pixel so the end pixels are fully covered.
+    let a = a - dir * 0.5;
+    let b = b + dir * 0.5;
+    fill_convex(
+        surface,
+        &[a + normal, b + normal, b - normal, a - normal],
+        color,
+    );
+}
+
+pub fn draw_rect<S>(surface: &mut S, pos: IVec2, size: IVec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let clip = surface.clip_rect();
+    if size.x <= 0 || size.y <= 0 || pos.x >= clip.max().x || pos.y >= clip.max().y {
+        return;
+    }
+    // The far corner can lie past i32::MAX. Edges beyond the clip aren't
+    // drawn anyway, so it's pulled in to just past the clip.
+    let end = (pos.as_i64vec2() + size.as_i64vec2() - 1)
+        .min(clip.max().as_i64vec2())
+        .as_ivec2();
+    if end.x < clip.min().x || end.y < clip.min().y {
+        return;
+    }
+    hline(surface, pos.x, end.x, pos.y, color);
+    if end.y != pos.y {
+        hline(surface, pos.x, end.x, end.y, color);
+    }
+    vline(surface, pos.x, pos.y + 1, end.y - 1, color);
+    if end.x != pos.x {
+        vline(surface, end.x, pos.y + 1, end.y - 1, color);
+    }
+}
+
+pub fn fill_rect<S, P>(surface: &mut S, pos: IVec2, size: IVec2, paint: P)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    let clip = surface.clip_rect();
+    let min = pos.max(clip.min());
+    let max = (pos.as_i64vec2() + size.as_i64vec2())
+        .min(clip.max().as_i64vec2())
+        .as_ivec2();
+    for y in min.y..max.y {
+        hline(surface, min.x, max.x - 1, y, &paint);
+    }
+}
+
+pub fn draw_circle<S>(surface: &mut S, center: IVec2, radius: i32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    draw_ellipse(surface, center, IVec2::splat(radius), color);
+}
+
+pub fn fill_circle<S>(surface: &mut S, center: IVec2, radius: i32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    fill_ellipse(surface, center, IVec2::splat(radius), color);
+}
+
+// Largest `x` so that `(x / rx)^2 + (y / ry)^2 <= 1`, biased by half a pixel.
+fn ellipse_span(radii: IVec2, y: i32) -> i32 {
+    let rx = radii.x as f32 + 0.5;
+    let ry = radii.y as f32 + 0.5;
+    let t = 1.0 - (y as f32 / ry).powi(2);
+    if t <= 0.0 {
+        return -1;
+    }
+    ((rx * t.sqrt()) - 0.5).round() as i32
+}
+
+// First and last offset in `0..=max` for which `center + offset` or
+// `center - offset` lies within `min..end`.
+fn visible_offsets(center: i32, max: i32, min: i32, end: i32) -> (i32, i32) {
+    let (center, min, last) = (center as i64, min as i64, end as i64 - 1);
+    let (first, last) = [(min - center, last - center), (center - last, center - min)]
+        .into_iter()
+        .map(|(first, last)| (first.max(0), last.min(max as i64)))
+        .filter(|(first, last)| first <= last)
+        .fold((i64::MAX, i64::MIN), |(a, b), (first, last)| {
+            (a.min(first), b.max(last))
+        });
+    if first > last {
+        return (0, -1);
+    }
+    (first as i32, last as i32)
+}
+
+// `hline` for coordinates that may lie outside the i32 range.
+fn hline_wide<S>(surface: &mut S, x0: i64, x1: i64, y: i64, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let clip = surface.clip_rect();
+    if y < clip.min().y as i64 || y >= clip.max().y as i64 {
+        return;
+    }
+    let x0 = x0.max(clip.min().x as i64);
+    let x1 = x1.min(clip.max().x as i64 - 1);
+    if x0 <= x1 {
+        hline(surface, x0 as i32, x1 as i32, y as i32, color);
+    }
+}
+
+pub fn draw_ellipse<S>(surface: &mut S, center: IVec2, radii: IVec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    if radii.x < 0 || radii.y < 0 {
+        return;
+    }
+    let clip = surface.clip_rect();
+    let (cx, cy) = (center.x as i64, center.y as i64);
+    // Each row covers the span between its own extent and the next row's
+    // extent, so the outline stays connected where it is nearly horizontal.
+    let (first, last) = visible_offsets(center.y, radii.y, clip.min().y, clip.max().y);
+    for y in first..=last {
+        let to = ellipse_span(radii, y);
+        if to < 0 {
+            continue;
+        }
+        let to = to as i64;
+        let from = (ellipse_span(radii, y.saturating_add(1)) as i64 + 1).clamp(0, to);
+        let rows: &[i64] = if y == 0 {
+            &[0]
+        } else {
+            &[-(y as i64), y as i64]
+        };
+        for row in rows {
+            hline_wide(surface, cx + from, cx + to, cy + row, color);
+            hline_wide(surface, cx - to, cx - from.max(1), cy + row, color);
+        }
+    }
+}
+
+pub fn fill_ellipse<S>(surface: &mut S, center: IVec2, radii: IVec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    if radii.x < 0 || radii.y < 0 {
+        return;
+    }
+    let clip = surface.clip_rect();
+    let (cx, cy) = (center.x as i64, center.y as i64);
+    let first = (cy - radii.y as i64).max(clip.min().y as i64);
+    let last = (cy + radii.y as i64).min(clip.max().y as i64 - 1);
+    for row in first..=last {
+        let x = ellipse_span(radii, (row - cy) as i32) as i64;
+        if x >= 0 {
+            hline_wide(surface, cx - x, cx + x, row, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    // Walks the whole line without any clipping.
+    fn reference_line(a: IVec2, b: IVec2) -> Vec<IVec2> {
+        let mut points = Vec::new();
+        let dx = (b.x - a.x).abs();
+        let dy = -(b.y - a.y).abs();
+        let step = (b - a).signum();
+        let mut err = dx + dy;
+        let mut pos = a;
+        loop {
+            points.push(pos);
+            if pos == b {
+                return points;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                pos.x += step.x;
+            }
+            if e2 <= dx {
+                err += dx;
+                pos.y += step.y;
+            }
+        }
+    }
+
+    fn check(a: IVec2, b: IVec2, clip: Rect) {
+        let mut image = Image::new(24, 20, Color::BLACK);
+        image.push_clip(clip);
+        image.draw_line(a, b, Color::WHITE);
+        let mut expected = Image::new(24, 20, Color::BLACK);
+        for pos in reference_line(a, b) {
+            if image.clip_rect().contains(pos) {
+                expected.set_pixel(pos, Color::WHITE);
+            }
+        }
+        assert_eq!(image.pixels(), expected.pixels(), "{a} {b} {clip:?}");
+    }
+
+    #[test]
+    fn clipped_line_matches_reference() {
+        let mut seed = 0x2545F491u32;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed % 61) as i32 - 20
+        };
+        let clips = [
+            Rect::from_size(ivec2(24, 20)),
+            Rect::new(ivec2(5, 3), ivec2(11, 9)),
+            Rect::new(ivec2(7, 7), ivec2(1, 1)),
+            Rect::ZERO,
+        ];
+        for _ in 0..5000 {
+            let (a, b) = (ivec2(next(), next()), ivec2(next(), next()));
+            for clip in clips {
+                check(a, b, clip);
+            }
+        }
+    }
+
+    #[test]
+    fn straight_and_degenerate_lines() {
+        let clip = Rect::new(ivec2(2, 2), ivec2(15, 12));
+        for (a, b) in [
+            (ivec2(-5, 4), ivec2(30, 4)),
+            (ivec2(30, 4), ivec2(-5, 4)),
+            (ivec2(6, -5), ivec2(6, 30)),
+            (ivec2(-3, -3), ivec2(30, 30)),
+            (ivec2(30, -3), ivec2(-3, 30)),
+            (ivec2(5, 5), ivec2(5, 5)),
+            (ivec2(0, 0), ivec2(0, 0)),
+        ] {
+            check(a, b, clip);
+        }
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        let mut image = Image::new(4, 4, Color::BLACK);
+        image.draw_line(ivec2(-200_000_000, 1), ivec2(200_000_000, 2), Color::WHITE);
+        image.draw_line(
+            ivec2(i32::MIN, i32::MIN),
+            ivec2(i32::MAX, i32::MAX),
+            Color::RED,
+        );
+        image.draw_line(ivec2(i32::MAX, 0), ivec2(i32::MIN, 0), Color::GREEN);
+        let expected = |pos: IVec2| match pos {
+            _ if pos.y == 0 => Color::GREEN,
+            _ if pos.x == pos.y => Color::RED,
+            _ if pos.y == 2 => Color::WHITE,
+            _ => Color::BLACK,
+        };
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(image.get_pixel(ivec2(x, y)), expected(ivec2(x, y)));
+            }
+        }
+    }
+
+    fn assert_pixels<F>(image: &Image, expected: F)
+    where
+        F: Fn(IVec2) -> Color,
+    {
+        for y in 0..image.size().y {
+            for x in 0..image.size().x {
+                let pos = ivec2(x, y);
+                assert_eq!(image.get_pixel(pos), expected(pos), "{pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn huge_rects() {
+        let max = IVec2::splat(i32::MAX);
+        let mut image = Image::new(8, 6, Color::BLACK);
+        image.fill_rect(ivec2(2, 3), max, Color::WHITE);
+        image.fill_rect(max, ivec2(i32::MAX, 5), Color::RED);
+        image.fill_rect(IVec2::splat(i32::MIN), max, Color::RED);
+        assert_pixels(&image, |pos| {
+            if pos.x >= 2 && pos.y >= 3 {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            }
+        });
+
+        let mut image = Image::new(8, 6, Color::BLACK);
+        image.draw_rect(ivec2(1, 1), max, Color::WHITE);
+        image.draw_rect(max - 1, max, Color::RED);
+        image.draw_rect(IVec2::splat(i32::MIN), max, Color::RED);
+        image.draw_rect(ivec2(-5, -5), max, Color::RED);
+        assert_pixels(&image, |pos| {
+            if (pos.x == 1 || pos.y == 1) && pos.cmpge(IVec2::ONE).all() {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            }
+        });
+    }
+
+    #[test]
+    fn huge_ellipses() {
+        let max = IVec2::splat(i32::MAX);
+        let mut image = Image::new(8, 6, Color::BLACK);
+        image.fill_ellipse(ivec2(4, 3), max, Color::WHITE);
+        assert_pixels(&image, |_| Color::WHITE);
+
+        let mut image = Image::new(8, 6, Color::BLACK);
+        image.draw_ellipse(ivec2(4, 3), max, Color::WHITE);
+        for center in [ivec2(i32::MIN, i32::MAX), max, IVec2::splat(i32::MIN)] {
+            image.fill_ellipse(center, max, Color::RED);
+            image.draw_ellipse(center, max, Color::RED);
+        }
+        assert_pixels(&image, |_| Color::BLACK);
+    }
+
+    #[test]
+    fn clipped_ellipses_match_unclipped() {
+        let clip = Rect::new(ivec2(3, 2), ivec2(9, 7));
+        for (center, radii) in [
+            (ivec2(8, 6), ivec2(5, 3)),
+            (ivec2(2, 12), ivec2(9, 7)),
+            (ivec2(15, -2), ivec2(6, 6)),
+            (ivec2(7, 5), ivec2(0, 4)),
+        ] {
+            for fill in [false, true] {
+                let draw = |image: &mut Image| {
+                    if fill {
+                        image.fill_ellipse(center, radii, Color::WHITE);
+                    } else {
+                        image.draw_ellipse(center, radii, Color::WHITE);
+                    }
+                };
+                let mut full = Image::new(16, 12, Color::BLACK);
+                draw(&mut full);
+                let mut image = Image::new(16, 12, Color::BLACK);
+                image.push_clip(clip);
+                draw(&mut image);
+                assert_pixels(&image, |pos| {
+                    if clip.contains(pos) {
+                        full.get_pixel(pos)
+                    } else {
+                        Color::BLACK
+                    }
+                });
+            }
+        }
+    }
+}
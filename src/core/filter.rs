@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+
+use crate::core::*;
+
+use super::srgb;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Bilinear,
+    Bicubic,
+    Lanczos,
+    Box,
+}
+
+impl Filter {
+    const fn radius(self) -> f32 {
+        match self {
+            Self::Nearest | Self::Box => 0.5,
+            Self::Bilinear => 1.0,
+            Self::Bicubic => 2.0,
+            Self::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Nearest | Self::Box => (x <= 0.5) as u8 as f32,
+            Self::Bilinear => (1.0 - x).max(0.0),
+            // Catmull-Rom spline.
+            Self::Bicubic => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Lanczos => {
+                if x < 1e-5 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    // Weight of the source pixel `[i, i + 1)` for a footprint centered at
+    // `center`, widened by `scale` when minifying.
+    fn tap(self, i: i32, center: f32, scale: f32) -> f32 {
+        if self == Self::Box {
+            let lo = (center - scale / 2.0).max(i as f32);
+            let hi = (center + scale / 2.0).min(i as f32 + 1.0);
+            (hi - lo).max(0.0)
+        } else {
+            self.weight((i as f32 + 0.5 - center) / scale)
+        }
+    }
+}
+
+// Samples `src` at `pos` (in pixel units, pixel centers at `.5`) without
+// reading outside of the `min..max` region. `scale` is the number of source
+// pixels covered by one destination pixel along each axis. With `linear` the
+// pixels are averaged in linear light instead of on their sRGB values.
+pub(crate) fn sample<S>(
+    src: &S,
+    filter: Filter,
+    linear: bool,
+    pos: Vec2,
+    scale: Vec2,
+    min: IVec2,
+    max: IVec2,
+) -> Color
+where
+    S: Surface + ?Sized,
+{
+    if filter == Filter::Nearest {
+        return src.get_pixel(pos.floor().as_ivec2().clamp(min, max - 1));
+    }
+    let scale = scale.max(Vec2::ONE);
+    let support = scale * filter.radius();
+    let lo = (pos - support).floor().as_ivec2().max(min);
+    let hi = (pos + support).ceil().as_ivec2().min(max);
+    let mut sum = [0.0f32; 4];
+    let mut total = 0.0;
+    for y in lo.y..hi.y {
+        let wy = filter.tap(y, pos.y, scale.y);
+        if wy == 0.0 {
+            continue;
+        }
+        for x in lo.x..hi.x {
+            let w = filter.tap(x, pos.x, scale.x) * wy;
+            if w == 0.0 {
+                continue;
+            }
+            let c = if linear {
+                srgb::premultiply(src.get_pixel(ivec2(x, y)).to_linear())
+            } else {
+                let c = src.get_pixel_premul(ivec2(x, y));
+                [c.r(), c.g(), c.b(), c.a()].map(|v| v as f32)
+            };
+            for (sum, c) in sum.iter_mut().zip(c) {
+                *sum += c * w;
+            }
+            total += w;
+        }
+    }
+    if total == 0.0 {
+        return src.get_pixel(pos.floor().as_ivec2().clamp(min, max - 1));
+    }
+    let average = sum.map(|v| v / total);
+    if linear {
+        Color::from_linear(srgb::unpremultiply(average))
+    } else {
+        premul_from_f32(average).to_color()
+    }
+}
+
+pub(crate) fn premul_from_f32(c: [f32; 4]) -> PremulColor {
+    let a = (c[3] + 0.5).clamp(0.0, 255.0) as u8;
+    let channel = |v: f32| ((v + 0.5).clamp(0.0, 255.0) as u8).min(a);
+    PremulColor::from_rgba(channel(c[0]), channel(c[1]), channel(c[2]), a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    fn pair(left: Color, right: Color) -> Image {
+        let mut image = Image::new(2, 1, left);
+        image.set_pixel(ivec2(1, 0), right);
+        image
+    }
+
+    fn average(src: &Image, filter: Filter, linear: bool) -> Color {
+        let (min, max) = (IVec2::ZERO, src.size());
+        sample(
+            src,
+            filter,
+            linear,
+            vec2(1.0, 0.5),
+            vec2(2.0, 1.0),
+            min,
+            max,
+        )
+    }
+
+    #[test]
+    fn averages_in_gamma_space_by_default() {
+        let src = pair(Color::BLACK, Color::WHITE);
+        for filter in [Filter::Bilinear, Filter::Box] {
+            assert_eq!(
+                average(&src, filter, false),
+                Color::from_rgb(0x80, 0x80, 0x80)
+            );
+        }
+    }
+
+    #[test]
+    fn averages_in_linear_light() {
+        let src = pair(Color::BLACK, Color::WHITE);
+        let gray = linear_to_srgb(0.5);
+        for filter in [Filter::Bilinear, Filter::Box] {
+            assert_eq!(
+                average(&src, filter, true),
+                Color::from_rgb(gray, gray, gray)
+            );
+        }
+    }
+
+    #[test]
+    fn linear_average_ignores_transparent_color() {
+        let src = pair(Color::RED, Color::GREEN.with_a(0));
+        for linear in [false, true] {
+            let color = average(&src, Filter::Box, linear);
+            assert_eq!(color, Color::RED.with_a(0x80), "linear: {linear}");
+        }
+    }
+}
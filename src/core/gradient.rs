@@ -0,0 +1,339 @@
+use std::f32::consts::TAU;
+
+use crate::core::*;
+
+use super::srgb;
+
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SpreadMode {
+    #[default]
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    Linear { start: Vec2, end: Vec2 },
+    Radial { center: Vec2, radius: f32 },
+    Conic { center: Vec2, angle: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    kind: GradientKind,
+    stops: Vec<ColorStop>,
+    spread: SpreadMode,
+    dither: bool,
+    linear: bool,
+}
+
+impl Gradient {
+    pub fn new(kind: GradientKind) -> Self {
+        Self {
+            kind,
+            stops: Vec::new(),
+            spread: SpreadMode::Pad,
+            dither: false,
+            linear: false,
+        }
+    }
+
+    pub fn linear(start: Vec2, end: Vec2) -> Self {
+        Self::new(GradientKind::Linear { start, end })
+    }
+
+    pub fn radial(center: Vec2, radius: f32) -> Self {
+        Self::new(GradientKind::Radial { center, radius })
+    }
+
+    // `angle` is the direction, in radians, where the conic gradient starts.
+    pub fn conic(center: Vec2, angle: f32) -> Self {
+        Self::new(GradientKind::Conic { center, angle })
+    }
+
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        let offset = offset.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|stop| stop.offset <= offset);
+        self.stops.insert(index, ColorStop { offset, color });
+        self
+    }
+
+    pub fn with_spread(mut self, spread: SpreadMode) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    pub fn with_linear(mut self, linear: bool) -> Self {
+        self.linear = linear;
+        self
+    }
+
+    pub fn kind(&self) -> GradientKind {
+        self.kind
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    fn offset_at(&self, p: Vec2) -> f32 {
+        let t = match self.kind {
+            GradientKind::Linear { start, end } => {
+                let d = end - start;
+                let len2 = d.length_squared();
+                if len2 == 0.0 {
+                    return 0.0;
+                }
+                (p - start).dot(d) / len2
+            }
+            GradientKind::Radial { center, radius } => {
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+                p.distance(center) / radius
+            }
+            GradientKind::Conic { center, angle } => {
+                let d = p - center;
+                return ((d.y.atan2(d.x) - angle) / TAU).rem_euclid(1.0);
+            }
+        };
+        match self.spread {
+            SpreadMode::Pad => t.clamp(0.0, 1.0),
+            SpreadMode::Repeat => t - t.floor(),
+            SpreadMode::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+
+    fn to_space(&self, color: Color) -> [f32; 4] {
+        if self.linear {
+            srgb::premultiply(color.to_linear())
+        } else {
+            srgb::premultiply(color.to_f32_array())
+        }
+    }
+
+    pub fn color_at_offset(&self, t: f32) -> Color {
+        self.shade(t, 0.0)
+    }
+
+    fn shade(&self, t: f32, threshold: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::TRANSPARENT,
+        };
+        let c = if t <= first.offset {
+            self.to_space(first.color)
+        } else if t >= last.offset {
+            self.to_space(last.color)
+        } else {
+            let i = self.stops.partition_point(|stop| stop.offset <= t);
+            let (a, b) = (self.stops[i - 1], self.stops[i]);
+            let local = (t - a.offset) / (b.offset - a.offset);
+            let (ca, cb) = (self.to_space(a.color), self.to_space(b.color));
+            std::array::from_fn(|i| ca[i] + (cb[i] - ca[i]) * local)
+        };
+        let mut c = srgb::unpremultiply(c);
+        if self.linear {
+            for v in &mut c[..3] {
+                *v = srgb::encode(v.clamp(0.0, 1.0));
+            }
+        }
+        let quantize = |v: f32| (v * 255.0 + 0.5 + threshold).clamp(0.0, 255.0) as u8;
+        Color::from_rgba(
+            quantize(c[0]),
+            quantize(c[1]),
+            quantize(c[2]),
+            quantize(c[3]),
+        )
+    }
+}
+
+impl Paint for Gradient {
+    fn color_at(&self, pos: IVec2) -> Color {
+        let t = self.offset_at(pos.as_vec2() + 0.5);
+        let threshold = if self.dither {
+            (BAYER[(pos.y & 3) as usize][(pos.x & 3) as usize] + 0.5) / 16.0 - 0.5
+        } else {
+            0.0
+        };
+        self.shade(t, threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(gradient: &Gradient, x: i32, y: i32) -> u8 {
+        gradient.color_at(ivec2(x, y)).r()
+    }
+
+    fn assert_offset(gradient: &Gradient, p: Vec2, expected: f32) {
+        let t = gradient.offset_at(p);
+        assert!((t - expected).abs() < 1e-5, "{p}: {t} != {expected}");
+    }
+
+    #[test]
+    fn stops() {
+        let gradient = Gradient::linear(Vec2::ZERO, Vec2::X)
+            .with_stop(1.0, Color::BLUE)
+            .with_stop(0.0, Color::RED)
+            .with_stop(0.5, Color::GREEN);
+        let offsets: Vec<_> = gradient.stops().iter().map(|stop| stop.offset).collect();
+        assert_eq!(offsets, [0.0, 0.5, 1.0]);
+        assert_eq!(gradient.color_at_offset(0.0), Color::RED);
+        assert_eq!(gradient.color_at_offset(0.25), Color::from_u32(0xFF808000));
+        assert_eq!(gradient.color_at_offset(0.5), Color::GREEN);
+        assert_eq!(gradient.color_at_offset(0.75), Color::from_u32(0xFF008080));
+        assert_eq!(gradient.color_at_offset(1.0), Color::BLUE);
+
+        // Offsets outside the first and last stops take their colors.
+        let gradient = Gradient::linear(Vec2::ZERO, Vec2::X)
+            .with_stop(0.25, Color::RED)
+            .with_stop(0.75, Color::BLUE);
+        assert_eq!(gradient.color_at_offset(0.0), Color::RED);
+        assert_eq!(gradient.color_at_offset(0.5), Color::from_u32(0xFF800080));
+        assert_eq!(gradient.color_at_offset(1.0), Color::BLUE);
+
+        // Stops sharing an offset make a hard edge, the later one winning.
+        let gradient = Gradient::linear(Vec2::ZERO, Vec2::X)
+            .with_stop(0.0, Color::BLACK)
+            .with_stop(0.5, Color::RED)
+            .with_stop(0.5, Color::BLUE)
+            .with_stop(1.0, Color::WHITE);
+        assert_eq!(gradient.color_at_offset(0.5), Color::BLUE);
+        assert_eq!(gradient.color_at_offset(0.499), Color::from_u32(0xFFFE0000));
+
+        // Colors are interpolated premultiplied, so transparent stops don't darken.
+        let gradient = Gradient::linear(Vec2::ZERO, Vec2::X)
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::TRANSPARENT);
+        assert_eq!(gradient.color_at_offset(0.5), Color::from_u32(0x80FF0000));
+
+        let empty = Gradient::linear(Vec2::ZERO, Vec2::X);
+        assert_eq!(empty.color_at_offset(0.5), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn spread_modes() {
+        let gradient = |spread| {
+            Gradient::linear(Vec2::ZERO, vec2(8.0, 0.0))
+                .with_stop(0.0, Color::BLACK)
+                .with_stop(1.0, Color::WHITE)
+                .with_spread(spread)
+        };
+        // Pixel centers fall at 1/16 steps on either side of the wrap points.
+        let cases = [
+            (SpreadMode::Pad, [0, 0, 16, 239, 255, 255]),
+            (SpreadMode::Repeat, [239, 239, 16, 239, 16, 16]),
+            (SpreadMode::Reflect, [239, 16, 16, 239, 239, 16]),
+        ];
+        for (spread, expected) in cases {
+            let gradient = gradient(spread);
+            let actual = [-9, -1, 0, 7, 8, 16].map(|x| gray(&gradient, x, 0));
+            assert_eq!(actual, expected, "{spread:?}");
+        }
+
+        let radial = |spread| Gradient::radial(Vec2::ZERO, 10.0).with_spread(spread);
+        for (spread, expected) in [
+            (SpreadMode::Pad, [0.5, 1.0, 1.0]),
+            (SpreadMode::Repeat, [0.5, 0.5, 0.0]),
+            (SpreadMode::Reflect, [0.5, 0.5, 0.0]),
+        ] {
+            let radial = radial(spread);
+            assert_offset(&radial, vec2(3.0, 4.0), expected[0]);
+            assert_offset(&radial, vec2(9.0, 12.0), expected[1]);
+            assert_offset(&radial, vec2(12.0, 16.0), expected[2]);
+        }
+    }
+
+    #[test]
+    fn conic_wraps_at_its_angle() {
+        use std::f32::consts::FRAC_PI_2;
+
+        let gradient = Gradient::conic(Vec2::ZERO, 0.0);
+        assert_offset(&gradient, vec2(1.0, 0.0), 0.0);
+        assert_offset(&gradient, vec2(0.0, 1.0), 0.25);
+        assert_offset(&gradient, vec2(-1.0, 0.0), 0.5);
+        assert_offset(&gradient, vec2(0.0, -1.0), 0.75);
+
+        let gradient = Gradient::conic(Vec2::ZERO, FRAC_PI_2);
+        assert_offset(&gradient, vec2(0.0, 1.0), 0.0);
+        assert_offset(&gradient, vec2(-1.0, 0.0), 0.25);
+        assert_offset(&gradient, vec2(1.0, 0.0), 0.75);
+        // Spread modes don't apply to conic gradients.
+        let gradient = gradient.with_spread(SpreadMode::Reflect);
+        assert_offset(&gradient, vec2(1.0, 0.0), 0.75);
+
+        // The seam sits between the rows just after and just before the start angle.
+        let gradient = Gradient::conic(vec2(8.5, 8.5), 0.0)
+            .with_stop(0.0, Color::BLACK)
+            .with_stop(1.0, Color::WHITE);
+        assert_eq!(gray(&gradient, 12, 8), 0);
+        assert!(gray(&gradient, 12, 9) < 16);
+        assert!(gray(&gradient, 12, 7) > 239);
+        assert_eq!(gray(&gradient, 4, 8), 128);
+    }
+
+    #[test]
+    fn dither() {
+        // A ramp from 0 to 1 over 64 pixels only covers half a quantization step.
+        let gradient = Gradient::linear(Vec2::ZERO, vec2(64.0, 0.0))
+            .with_stop(0.0, Color::BLACK)
+            .with_stop(1.0, Color::from_rgba(1, 1, 1, 255));
+        let sum = |gradient: &Gradient, x: i32| (0..4).map(|y| gray(gradient, x, y) as u32).sum();
+
+        let plain: Vec<u32> = (0..64).map(|x| sum(&gradient, x)).collect();
+        assert!(plain[..32].iter().all(|&v| v == 0));
+        assert!(plain[32..].iter().all(|&v| v == 4));
+
+        let gradient = gradient.with_dither(true);
+        let dithered: Vec<u32> = (0..64).map(|x| sum(&gradient, x)).collect();
+        assert!(dithered.iter().all(|&v| v <= 4));
+        assert!(dithered[..32].iter().any(|&v| v > 0));
+        assert!(dithered[32..].iter().any(|&v| v < 4));
+        // Each 4x4 tile averages to the ramp's value at its center.
+        for tile in 0..16 {
+            let total: u32 = dithered[tile * 4..tile * 4 + 4].iter().sum();
+            let expected = (tile as f32 * 4.0 + 2.0) / 64.0 * 16.0;
+            assert!((total as f32 - expected).abs() <= 1.0, "{tile}: {total}");
+        }
+
+        // Dithering never moves a channel more than one step.
+        let ramp = |dither| {
+            Gradient::linear(Vec2::ZERO, vec2(100.0, 0.0))
+                .with_stop(0.0, Color::BLACK)
+                .with_stop(1.0, Color::WHITE)
+                .with_dither(dither)
+        };
+        let (plain, dithered) = (ramp(false), ramp(true));
+        for y in 0..4 {
+            for x in 0..100 {
+                let (a, b) = (gray(&plain, x, y) as i32, gray(&dithered, x, y) as i32);
+                assert!((a - b).abs() <= 1, "{x} {y}: {a} {b}");
+            }
+        }
+    }
+}
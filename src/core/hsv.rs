@@ -0,0 +1,178 @@
+use crate::core::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+    pub a: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+    pub a: f32,
+}
+
+// Returns hue in degrees, max and min of the normalized RGB channels.
+fn hue_max_min(color: Color) -> (f32, f32, f32) {
+    let [r, g, b, _] = color.to_f32_array();
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (h, max, min)
+}
+
+fn from_hue_chroma(h: f32, c: f32, m: f32, a: f32) -> Color {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as i32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    Color::from_f32_array([r + m, g + m, b + m, a])
+}
+
+impl Hsv {
+    pub const fn new(h: f32, s: f32, v: f32) -> Self {
+        Self { h, s, v, a: 1.0 }
+    }
+
+    pub fn rotate_hue(self, degrees: f32) -> Self {
+        Self {
+            h: (self.h + degrees).rem_euclid(360.0),
+            ..self
+        }
+    }
+}
+
+impl Hsl {
+    pub const fn new(h: f32, s: f32, l: f32) -> Self {
+        Self { h, s, l, a: 1.0 }
+    }
+
+    pub fn rotate_hue(self, degrees: f32) -> Self {
+        Self {
+            h: (self.h + degrees).rem_euclid(360.0),
+            ..self
+        }
+    }
+}
+
+impl From<Color> for Hsv {
+    fn from(color: Color) -> Self {
+        let (h, max, min) = hue_max_min(color);
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        Self {
+            h,
+            s,
+            v: max,
+            a: color.a() as f32 / 255.0,
+        }
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(hsv: Hsv) -> Self {
+        let s = hsv.s.clamp(0.0, 1.0);
+        let v = hsv.v.clamp(0.0, 1.0);
+        let c = v * s;
+        from_hue_chroma(hsv.h, c, v - c, hsv.a)
+    }
+}
+
+impl From<Color> for Hsl {
+    fn from(color: Color) -> Self {
+        let (h, max, min) = hue_max_min(color);
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        Self {
+            h,
+            s,
+            l,
+            a: color.a() as f32 / 255.0,
+        }
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(hsl: Hsl) -> Self {
+        let s = hsl.s.clamp(0.0, 1.0);
+        let l = hsl.l.clamp(0.0, 1.0);
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        from_hue_chroma(hsl.h, c, l - c / 2.0, hsl.a)
+    }
+}
+
+impl Color {
+    pub fn to_hsv(self) -> Hsv {
+        self.into()
+    }
+
+    pub fn from_hsv(hsv: Hsv) -> Self {
+        hsv.into()
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        self.into()
+    }
+
+    pub fn from_hsl(hsl: Hsl) -> Self {
+        hsl.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> impl Iterator<Item = Color> {
+        let steps = || (0..=255u8).step_by(5);
+        steps().flat_map(move |r| {
+            steps().flat_map(move |g| steps().map(move |b| Color::from_rgba(r, g, b, r ^ g)))
+        })
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in grid() {
+            assert_eq!(Color::from_hsv(color.to_hsv()), color);
+        }
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for color in grid() {
+            assert_eq!(Color::from_hsl(color.to_hsl()), color);
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        assert_eq!(Color::RED.to_hsv(), Hsv::new(0.0, 1.0, 1.0));
+        assert_eq!(Color::from_hsv(Hsv::new(120.0, 1.0, 1.0)), Color::GREEN);
+        assert_eq!(Color::from_hsl(Hsl::new(240.0, 1.0, 0.5)), Color::BLUE);
+        assert_eq!(Color::WHITE.to_hsl().s, 0.0);
+        assert_eq!(Color::from_hsv(Hsv::new(-240.0, 1.0, 1.0)), Color::GREEN);
+        assert_eq!(Hsv::new(300.0, 1.0, 1.0).rotate_hue(90.0).h, 30.0);
+    }
+}
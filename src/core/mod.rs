@@ -1,8 +1,36 @@
+mod blend;
 mod blit;
+mod clip;
 mod color;
+mod draw;
+mod filter;
+mod gradient;
+mod hsv;
+mod paint;
+mod path;
+mod polygon;
+mod premul;
+mod rect;
+pub(crate) mod simd;
+mod smooth;
+mod srgb;
 mod surface;
+mod view;
 
-pub use color::Color;
+pub use blend::BlendMode;
+pub use blit::BlitOptions;
+pub use clip::ClipStack;
+pub use color::{Color, ParseColorError};
+pub use filter::Filter;
+pub use gradient::{ColorStop, Gradient, GradientKind, SpreadMode};
+pub use hsv::{Hsl, Hsv};
+pub use paint::Paint;
+pub use path::{LineCap, LineJoin, Path, Polyline, StrokeStyle};
+pub use polygon::FillRule;
+pub use premul::PremulColor;
+pub use rect::Rect;
+pub use srgb::{linear_to_srgb, srgb_to_linear};
 pub use surface::Surface;
+pub use view::SubSurface;
 
-pub use glam::{IVec2, Vec2, ivec2, vec2};
+pub use glam::{Affine2, IVec2, Vec2, ivec2, vec2};
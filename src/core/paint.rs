@@ -0,0 +1,33 @@
+use crate::core::*;
+
+pub trait Paint {
+    fn color_at(&self, pos: IVec2) -> Color;
+
+    // The color of every position, if it doesn't depend on the position.
+    fn solid_color(&self) -> Option<Color> {
+        None
+    }
+}
+
+impl Paint for Color {
+    fn color_at(&self, _pos: IVec2) -> Color {
+        *self
+    }
+
+    fn solid_color(&self) -> Option<Color> {
+        Some(*self)
+    }
+}
+
+impl<P> Paint for &P
+where
+    P: Paint + ?Sized,
+{
+    fn color_at(&self, pos: IVec2) -> Color {
+        (**self).color_at(pos)
+    }
+
+    fn solid_color(&self) -> Option<Color> {
+        (**self).solid_color()
+    }
+}
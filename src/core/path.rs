@@ -0,0 +1,526 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use crate::core::*;
+
+use super::polygon;
+
+pub const DEFAULT_TOLERANCE: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verb {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadTo(Vec2, Vec2),
+    CubicTo(Vec2, Vec2, Vec2),
+    Close,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    verbs: Vec<Verb>,
+    start: Vec2,
+    current: Vec2,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl AsRef<[Vec2]> for Polyline {
+    fn as_ref(&self) -> &[Vec2] {
+        &self.points
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verbs.is_empty()
+    }
+
+    pub fn current_point(&self) -> Vec2 {
+        self.current
+    }
+
+    pub fn move_to(&mut self, to: Vec2) -> &mut Self {
+        self.verbs.push(Verb::MoveTo(to));
+        self.start = to;
+        self.current = to;
+        self
+    }
+
+    fn ensure_started(&mut self) {
+        if self.verbs.is_empty() || matches!(self.verbs.last(), Some(Verb::Close)) {
+            self.verbs.push(Verb::MoveTo(self.current));
+            self.start = self.current;
+        }
+    }
+
+    pub fn line_to(&mut self, to: Vec2) -> &mut Self {
+        self.ensure_started();
+        self.verbs.push(Verb::LineTo(to));
+        self.current = to;
+        self
+    }
+
+    pub fn quad_to(&mut self, ctrl: Vec2, to: Vec2) -> &mut Self {
+        self.ensure_started();
+        self.verbs.push(Verb::QuadTo(ctrl, to));
+        self.current = to;
+        self
+    }
+
+    pub fn cubic_to(&mut self, ctrl1: Vec2, ctrl2: Vec2, to: Vec2) -> &mut Self {
+        self.ensure_started();
+        self.verbs.push(Verb::CubicTo(ctrl1, ctrl2, to));
+        self.current = to;
+        self
+    }
+
+    // Elliptical arc with the same parameters as the SVG `A` command.
+    pub fn arc_to(
+        &mut self,
+        radii: Vec2,
+        x_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Vec2,
+    ) -> &mut Self {
+        let from = self.current;
+        let mut radii = radii.abs();
+        if from == to {
+            return self;
+        }
+        if radii.x == 0.0 || radii.y == 0.0 {
+            return self.line_to(to);
+        }
+
+        // Endpoint to center parameterization, SVG 1.1 appendix F.6.5.
+        let (sin, cos) = x_rotation.to_radians().sin_cos();
+        let rotate = |v: Vec2| vec2(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+        let unrotate = |v: Vec2| vec2(cos * v.x + sin * v.y, -sin * v.x + cos * v.y);
+        let p = unrotate((from - to) / 2.0);
+        let lambda = (p / radii).length_squared();
+        if lambda > 1.0 {
+            radii *= lambda.sqrt();
+        }
+        let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+        let num = rx2 * ry2 - rx2 * p.y * p.y - ry2 * p.x * p.x;
+        let den = rx2 * p.y * p.y + ry2 * p.x * p.x;
+        let mut k = (num / den).max(0.0).sqrt();
+        if large_arc == sweep {
+            k = -k;
+        }
+        let c = vec2(k * radii.x * p.y / radii.y, -k * radii.y * p.x / radii.x);
+        let center = rotate(c) + (from + to) / 2.0;
+
+        let u = (p - c) / radii;
+        let v = (-p - c) / radii;
+        let start_angle = u.y.atan2(u.x);
+        let mut sweep_angle = u.angle_to(v);
+        if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= TAU;
+        } else if sweep && sweep_angle < 0.0 {
+            sweep_angle += TAU;
+        }
+
+        let segments = (sweep_angle.abs() / FRAC_PI_2).ceil().max(1.0) as i32;
+        let delta = sweep_angle / segments as f32;
+        let t = 4.0 / 3.0 * (delta / 4.0).tan();
+        let point = |angle: f32| {
+            let (s, c) = angle.sin_cos();
+            (vec2(c, s), vec2(-s, c))
+        };
+        for i in 0..segments {
+            let a0 = start_angle + delta * i as f32;
+            let a1 = a0 + delta;
+            let (p0, d0) = point(a0);
+            let (p1, d1) = point(a1);
+            let ctrl1 = center + rotate((p0 + d0 * t) * radii);
+            let ctrl2 = center + rotate((p1 - d1 * t) * radii);
+            let end = if i == segments - 1 {
+                to
+            } else {
+                center + rotate(p1 * radii)
+            };
+            self.cubic_to(ctrl1, ctrl2, end);
+        }
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        if !self.verbs.is_empty() && !matches!(self.verbs.last(), Some(Verb::Close)) {
+            self.verbs.push(Verb::Close);
+        }
+        self.current = self.start;
+        self
+    }
+
+    // Bounds of all points, including control points, so they also contain
+    // every curve.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let mut points = self.verbs.iter().flat_map(|verb| match *verb {
+            Verb::MoveTo(to) | Verb::LineTo(to) => vec![to],
+            Verb::QuadTo(ctrl, to) => vec![ctrl, to],
+            Verb::CubicTo(ctrl1, ctrl2, to) => vec![ctrl1, ctrl2, to],
+            Verb::Close => vec![],
+        });
+        let first = points.next()?;
+        Some(points.fold((first, first), |(min, max), p| (min.min(p), max.max(p))))
+    }
+
+    pub fn transform(&self, transform: &Affine2) -> Self {
+        let map = |p: Vec2| transform.transform_point2(p);
+        Self {
+            verbs: self
+                .verbs
+                .iter()
+                .map(|verb| match *verb {
+                    Verb::MoveTo(to) => Verb::MoveTo(map(to)),
+                    Verb::LineTo(to) => Verb::LineTo(map(to)),
+                    Verb::QuadTo(ctrl, to) => Verb::QuadTo(map(ctrl), map(to)),
+                    Verb::CubicTo(ctrl1, ctrl2, to) => {
+                        Verb::CubicTo(map(ctrl1), map(ctrl2), map(to))
+                    }
+                    Verb::Close => Verb::Close,
+                })
+                .collect(),
+            start: map(self.start),
+            current: map(self.current),
+        }
+    }
+
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(0.001);
+        let mut result = Vec::new();
+        let mut line = Polyline::default();
+        let mut current = Vec2::ZERO;
+        for verb in &self.verbs {
+            match *verb {
+                Verb::MoveTo(to) => {
+                    if line.points.len() > 1 {
+                        result.push(std::mem::take(&mut line));
+                    }
+                    line = Polyline {
+                        points: vec![to],
+                        closed: false,
+                    };
+                    current = to;
+                }
+                Verb::LineTo(to) => {
+                    line.points.push(to);
+                    current = to;
+                }
+                Verb::QuadTo(ctrl, to) => {
+                    let dd = (current - 2.0 * ctrl + to).length();
+                    let n = segment_count(dd / (4.0 * tolerance));
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        line.points
+                            .push(current * mt * mt + ctrl * 2.0 * mt * t + to * t * t);
+                    }
+                    current = to;
+                }
+                Verb::CubicTo(ctrl1, ctrl2, to) => {
+                    let dd = (current - 2.0 * ctrl1 + ctrl2)
+                        .length()
+                        .max((ctrl1 - 2.0 * ctrl2 + to).length());
+                    let n = segment_count(3.0 * dd / (4.0 * tolerance));
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        line.points.push(
+                            current * mt * mt * mt
+                                + ctrl1 * 3.0 * mt * mt * t
+                                + ctrl2 * 3.0 * mt * t * t
+                                + to * t * t * t,
+                        );
+                    }
+                    current = to;
+                }
+                Verb::Close => {
+                    line.closed = true;
+                    let start = line.points.first().copied().unwrap_or(current);
+                    if line.points.len() > 1 {
+                        result.push(std::mem::take(&mut line));
+                    }
+                    line = Polyline {
+                        points: vec![start],
+                        closed: false,
+                    };
+                    current = start;
+                }
+            }
+        }
+        if line.points.len() > 1 {
+            result.push(line);
+        }
+        result
+    }
+
+    pub fn stroke(&self, style: &StrokeStyle, tolerance: f32) -> Vec<Vec<Vec2>> {
+        let mut stroker = Stroker {
+            half_width: style.width / 2.0,
+            style,
+            tolerance: tolerance.max(0.001),
+            polygons: Vec::new(),
+        };
+        if stroker.half_width > 0.0 {
+            for line in self.flatten(tolerance) {
+                stroker.stroke_polyline(line);
+            }
+        }
+        stroker.polygons
+    }
+}
+
+fn segment_count(v: f32) -> usize {
+    (v.sqrt().ceil() as usize).clamp(1, 1024)
+}
+
+struct Stroker<'a> {
+    half_width: f32,
+    style: &'a StrokeStyle,
+    tolerance: f32,
+    polygons: Vec<Vec<Vec2>>,
+}
+
+impl Stroker<'_> {
+    // Polygons are all emitted with the same orientation so that filling them
+    // with the non-zero rule yields their union.
+    fn push(&mut self, mut polygon: Vec<Vec2>) {
+        let mut area = 0.0;
+        for i in 0..polygon.len() {
+            let p = polygon[i];
+            let q = polygon[(i + 1) % polygon.len()];
+            area += p.perp_dot(q);
+        }
+        if area < 0.0 {
+            polygon.reverse();
+        }
+        self.polygons.push(polygon);
+    }
+
+    fn push_circle(&mut self, center: Vec2) {
+        let r = self.half_width;
+        let step = 2.0 * (1.0 - self.tolerance / r).clamp(-1.0, 1.0).acos();
+        let n = ((TAU / step.max(0.01)).ceil() as usize).clamp(8, 256);
+        self.push(
+            (0..n)
+                .map(|i| {
+                    let (s, c) = (i as f32 / n as f32 * TAU).sin_cos();
+                    center + vec2(c, s) * r
+                })
+                .collect(),
+        );
+    }
+
+    fn stroke_polyline(&mut self, mut line: Polyline) {
+        line.points.dedup();
+        if line.closed && line.points.len() > 1 && line.points.first() == line.points.last() {
+            line.points.pop();
+        }
+        let points = &line.points;
+        let n = points.len();
+        if n < 2 {
+            if let Some(&p) = points.first()
+                && !line.closed
+            {
+                match self.style.cap {
+                    LineCap::Round => self.push_circle(p),
+                    LineCap::Square => {
+                        let h = self.half_width;
+                        self.push(vec![
+                            p + vec2(-h, -h),
+                            p + vec2(h, -h),
+                            p + vec2(h, h),
+                            p + vec2(-h, h),
+                        ]);
+                    }
+                    LineCap::Butt => {}
+                }
+            }
+            return;
+        }
+
+        let segments = if line.closed { n } else { n - 1 };
+        for i in 0..segments {
+            let a = points[i];
+            let b = points[(i + 1) % n];
+            let normal = (b - a).normalize().perp() * self.half_width;
+            self.push(vec![a + normal, b + normal, b - normal, a - normal]);
+        }
+
+        let joins = if line.closed { 0..n } else { 1..n - 1 };
+        for i in joins {
+            let prev = points[(i + n - 1) % n];
+            let p = points[i];
+            let next = points[(i + 1) % n];
+            self.join(prev, p, next);
+        }
+
+        if !line.closed {
+            self.cap(points[1], points[0]);
+            self.cap(points[n - 2], points[n - 1]);
+        }
+    }
+
+    fn join(&mut self, prev: Vec2, p: Vec2, next: Vec2) {
+        let d0 = (p - prev).normalize();
+        let d1 = (next - p).normalize();
+        let cross = d0.perp_dot(d1);
+        if cross.abs() < 1e-6 && d0.dot(d1) > 0.0 {
+            return;
+        }
+        let side = if cross > 0.0 { -1.0 } else { 1.0 };
+        let n0 = d0.perp() * self.half_width * side;
+        let n1 = d1.perp() * self.half_width * side;
+        match self.style.join {
+            LineJoin::Round => self.push_circle(p),
+            LineJoin::Bevel => self.push(vec![p, p + n0, p + n1]),
+            LineJoin::Miter => {
+                let mid = (n0 + n1) / 2.0;
+                let len2 = mid.length_squared();
+                let ratio = self.half_width / len2.sqrt();
+                if len2 > 0.0 && ratio <= self.style.miter_limit {
+                    let miter = mid * (self.half_width * self.half_width / len2);
+                    self.push(vec![p, p + n0, p + miter, p + n1]);
+                } else {
+                    self.push(vec![p, p + n0, p + n1]);
+                }
+            }
+        }
+    }
+
+    fn cap(&mut self, from: Vec2, end: Vec2) {
+        let dir = (end - from).normalize();
+        let normal = dir.perp() * self.half_width;
+        match self.style.cap {
+            LineCap::Butt => {}
+            LineCap::Round => self.push_circle(end),
+            LineCap::Square => {
+                let out = end + dir * self.half_width;
+                self.push(vec![end + normal, out + normal, out - normal, end - normal]);
+            }
+        }
+    }
+}
+
+pub fn fill_path<S, P>(surface: &mut S, path: &Path, paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    polygon::fill_contours(surface, &path.flatten(DEFAULT_TOLERANCE), paint, rule);
+}
+
+pub fn fill_path_aa<S, P>(surface: &mut S, path: &Path, paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    polygon::fill_contours_aa(surface, &path.flatten(DEFAULT_TOLERANCE), paint, rule);
+}
+
+pub fn stroke_path<S, P>(surface: &mut S, path: &Path, style: &StrokeStyle, paint: P)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    let polygons = path.stroke(style, DEFAULT_TOLERANCE);
+    polygon::fill_contours(surface, &polygons, paint, FillRule::NonZero);
+}
+
+pub fn stroke_path_aa<S, P>(surface: &mut S, path: &Path, style: &StrokeStyle, paint: P)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    let polygons = path.stroke(style, DEFAULT_TOLERANCE);
+    polygon::fill_contours_aa(surface, &polygons, paint, FillRule::NonZero);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_subpaths() {
+        let mut path = Path::new();
+        path.move_to(vec2(0.0, 0.0))
+            .line_to(vec2(4.0, 0.0))
+            .line_to(vec2(4.0, 4.0))
+            .close()
+            .move_to(vec2(10.0, 0.0))
+            .line_to(vec2(10.0, 4.0));
+        let lines = path.flatten(DEFAULT_TOLERANCE);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].closed);
+        assert_eq!(
+            lines[0].points,
+            [vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 4.0)]
+        );
+        assert!(!lines[1].closed);
+        assert_eq!(lines[1].points, [vec2(10.0, 0.0), vec2(10.0, 4.0)]);
+    }
+
+    #[test]
+    fn close_on_single_point_does_not_leak() {
+        let mut path = Path::new();
+        path.move_to(vec2(0.0, 0.0))
+            .close()
+            .move_to(vec2(10.0, 0.0))
+            .line_to(vec2(10.0, 4.0))
+            .line_to(vec2(14.0, 4.0));
+        let lines = path.flatten(DEFAULT_TOLERANCE);
+        assert_eq!(lines.len(), 1);
+        assert!(!lines[0].closed);
+
+        let mut path = Path::new();
+        path.move_to(vec2(0.0, 0.0)).close().line_to(vec2(3.0, 0.0));
+        let lines = path.flatten(DEFAULT_TOLERANCE);
+        assert_eq!(lines.len(), 1);
+        assert!(!lines[0].closed);
+    }
+}
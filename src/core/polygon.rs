@@ -0,0 +1,310 @@
+use crate::core::*;
+
+use super::draw::hline;
+use super::smooth::put_coverage;
+
+const SUBSAMPLES: i32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    const fn is_inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    top: Vec2,
+    bottom: Vec2,
+    winding: i32,
+}
+
+impl Edge {
+    fn x_at(&self, y: f32) -> f32 {
+        self.top.x + (y - self.top.y) / (self.bottom.y - self.top.y) * (self.bottom.x - self.top.x)
+    }
+}
+
+fn build_edges<C>(contours: &[C]) -> Vec<Edge>
+where
+    C: AsRef<[Vec2]>,
+{
+    let mut edges = Vec::new();
+    for contour in contours {
+        let points = contour.as_ref();
+        for i in 0..points.len() {
+            let p = points[i];
+            let q = points[(i + 1) % points.len()];
+            if p.y == q.y || !p.is_finite() || !q.is_finite() {
+                continue;
+            }
+            edges.push(if p.y < q.y {
+                Edge {
+                    top: p,
+                    bottom: q,
+                    winding: 1,
+                }
+            } else {
+                Edge {
+                    top: q,
+                    bottom: p,
+                    winding: -1,
+                }
+            });
+        }
+    }
+    edges
+}
+
+// Calls `span` with every inside interval of the horizontal line at `y`.
+fn for_each_span<F>(
+    edges: &[Edge],
+    y: f32,
+    rule: FillRule,
+    crossings: &mut Vec<(f32, i32)>,
+    mut span: F,
+) where
+    F: FnMut(f32, f32),
+{
+    crossings.clear();
+    crossings.extend(
+        edges
+            .iter()
+            .filter(|edge| edge.top.y <= y && y < edge.bottom.y)
+            .map(|edge| (edge.x_at(y), edge.winding)),
+    );
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut winding = 0;
+    for pair in crossings.windows(2) {
+        winding += pair[0].1;
+        if rule.is_inside(winding) && pair[0].0 < pair[1].0 {
+            span(pair[0].0, pair[1].0);
+        }
+    }
+}
+
+fn y_range(edges: &[Edge], clip: Rect) -> (i32, i32) {
+    let (min, max) = edges
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), e| {
+            (min.min(e.top.y), max.max(e.bottom.y))
+        });
+    let (y0, y1) = (clip.min().y, clip.max().y);
+    ((min.floor() as i32).max(y0), (max.ceil() as i32).min(y1))
+}
+
+pub fn fill_contours<S, C, P>(surface: &mut S, contours: &[C], paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    C: AsRef<[Vec2]>,
+    P: Paint,
+{
+    let edges = build_edges(contours);
+    let (y0, y1) = y_range(&edges, surface.clip_rect());
+    let mut crossings = Vec::new();
+    for y in y0..y1 {
+        for_each_span(&edges, y as f32 + 0.5, rule, &mut crossings, |a, b| {
+            let x0 = (a - 0.5).ceil() as i32;
+            let x1 = (b - 0.5).ceil() as i32 - 1;
+            hline(surface, x0, x1, y, &paint);
+        });
+    }
+}
+
+pub fn fill_contours_aa<S, C, P>(surface: &mut S, contours: &[C], paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    C: AsRef<[Vec2]>,
+    P: Paint,
+{
+    let edges = build_edges(contours);
+    let width = surface.size().x;
+    if width <= 0 {
+        return;
+    }
+    let (y0, y1) = y_range(&edges, surface.clip_rect());
+    let mut crossings = Vec::new();
+    let mut coverage = vec![0.0f32; width as usize];
+    let weight = 1.0 / SUBSAMPLES as f32;
+    for y in y0..y1 {
+        coverage.fill(0.0);
+        let (mut min_x, mut max_x) = (width, 0);
+        for sample in 0..SUBSAMPLES {
+            let sy = y as f32 + (sample as f32 + 0.5) * weight;
+            for_each_span(&edges, sy, rule, &mut crossings, |a, b| {
+                let a = a.clamp(0.0, width as f32);
+                let b = b.clamp(0.0, width as f32);
+                if a >= b {
+                    return;
+                }
+                let i0 = a as i32;
+                let i1 = b as i32;
+                min_x = min_x.min(i0);
+                max_x = max_x.max(i1.min(width - 1));
+                if i0 == i1 {
+                    coverage[i0 as usize] += (b - a) * weight;
+                    return;
+                }
+                coverage[i0 as usize] += (i0 as f32 + 1.0 - a) * weight;
+                for c in &mut coverage[(i0 + 1) as usize..i1 as usize] {
+                    *c += weight;
+                }
+                if i1 < width {
+                    coverage[i1 as usize] += (b - i1 as f32) * weight;
+                }
+            });
+        }
+        for x in min_x..=max_x {
+            let coverage = coverage[x as usize];
+            if coverage > 0.0 {
+                let pos = ivec2(x, y);
+                put_coverage(surface, pos, paint.color_at(pos), coverage);
+            }
+        }
+    }
+}
+
+pub fn fill_polygon<S, P>(surface: &mut S, points: &[Vec2], paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    fill_contours(surface, &[points], paint, rule);
+}
+
+pub fn fill_polygon_aa<S, P>(surface: &mut S, points: &[Vec2], paint: P, rule: FillRule)
+where
+    S: Surface + ?Sized,
+    P: Paint,
+{
+    fill_contours_aa(surface, &[points], paint, rule);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    fn square(min: f32, max: f32) -> [Vec2; 4] {
+        [
+            vec2(min, min),
+            vec2(max, min),
+            vec2(max, max),
+            vec2(min, max),
+        ]
+    }
+
+    fn fill<C>(contours: &[C], rule: FillRule, aa: bool) -> Image
+    where
+        C: AsRef<[Vec2]>,
+    {
+        let mut image = Image::new(24, 24, Color::BLACK);
+        if aa {
+            image.fill_contours_aa(contours, Color::WHITE, rule);
+        } else {
+            image.fill_contours(contours, Color::WHITE, rule);
+        }
+        image
+    }
+
+    fn filled(image: &Image, x: i32, y: i32) -> bool {
+        image.get_pixel(ivec2(x, y)) == Color::WHITE
+    }
+
+    #[test]
+    fn rules_agree_on_simple_shapes() {
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            for aa in [false, true] {
+                let image = fill(&[square(2.0, 10.0)], rule, aa);
+                for y in 0..24 {
+                    for x in 0..24 {
+                        let inside = (2..10).contains(&x) && (2..10).contains(&y);
+                        assert_eq!(filled(&image, x, y), inside, "{x} {y}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn holes() {
+        let outer = square(2.0, 20.0);
+        let same = square(8.0, 14.0);
+        let mut reversed = same;
+        reversed.reverse();
+        for aa in [false, true] {
+            // A hole wound the same way as the outline only shows under even-odd.
+            let image = fill(&[outer, same], FillRule::NonZero, aa);
+            assert!(filled(&image, 10, 10) && filled(&image, 4, 4));
+            let image = fill(&[outer, same], FillRule::EvenOdd, aa);
+            assert!(!filled(&image, 10, 10) && filled(&image, 4, 4));
+            // Wound the other way, it's a hole under both rules.
+            for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+                let image = fill(&[outer, reversed], rule, aa);
+                assert!(!filled(&image, 10, 10) && filled(&image, 4, 4));
+                assert_eq!(image.get_pixel(ivec2(8, 8)), Color::BLACK);
+                assert!(filled(&image, 7, 7));
+            }
+        }
+    }
+
+    #[test]
+    fn self_intersections() {
+        // A pentagram winds twice around its center.
+        let star: Vec<_> = (0..5)
+            .map(|i| {
+                let angle = (i * 2) as f32 * std::f32::consts::TAU / 5.0;
+                vec2(12.0, 12.0) + 10.0 * vec2(angle.sin(), -angle.cos())
+            })
+            .collect();
+        for aa in [false, true] {
+            let image = fill(&[&star], FillRule::NonZero, aa);
+            assert!(filled(&image, 11, 12) && filled(&image, 7, 10));
+            let image = fill(&[&star], FillRule::EvenOdd, aa);
+            assert!(!filled(&image, 11, 12) && filled(&image, 7, 10));
+        }
+
+        // A bow tie winds once each way, so both rules fill both halves.
+        let bow_tie = [
+            vec2(2.0, 2.0),
+            vec2(20.0, 20.0),
+            vec2(20.0, 2.0),
+            vec2(2.0, 20.0),
+        ];
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let image = fill(&[bow_tie], rule, false);
+            assert!(filled(&image, 17, 11) && filled(&image, 4, 11));
+            assert!(!filled(&image, 11, 4) && !filled(&image, 11, 17));
+        }
+    }
+
+    #[test]
+    fn anti_aliased_coverage() {
+        let shape = [
+            vec2(2.25, 2.0),
+            vec2(8.0, 2.0),
+            vec2(8.0, 4.3),
+            vec2(2.25, 4.3),
+        ];
+        let image = fill(&[shape], FillRule::NonZero, true);
+        let pixel = |x, y| image.get_pixel(ivec2(x, y)).as_u32();
+        // Whole pixels are fully covered.
+        assert_eq!(pixel(5, 3), 0xFFFFFFFF);
+        // A quarter of the first column is left out.
+        assert_eq!(pixel(2, 3), 0xFFBFBFBF);
+        // Rows are sampled 16 times, so 0.3 of a row counts as 5/16.
+        assert_eq!(pixel(5, 4), 0xFF505050);
+        assert_eq!(pixel(2, 4), 0xFF3C3C3C);
+        assert_eq!(pixel(8, 3), 0xFF000000);
+        assert_eq!(pixel(5, 5), 0xFF000000);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::core::*;
+
+// Rounded `a * b / 255` for values in 0..=255.
+pub(crate) const fn mul(a: u32, b: u32) -> u32 {
+    let x = a * b + 128;
+    (x + (x >> 8)) >> 8
+}
+
+const fn div(v: u32, a: u32) -> u8 {
+    let v = (v * 255 + a / 2) / a;
+    if v > 255 { 255 } else { v as u8 }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PremulColor(u32);
+
+impl PremulColor {
+    pub const TRANSPARENT: Self = Self(0);
+
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        debug_assert!(r <= a && g <= a && b <= a);
+        Self(((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32))
+    }
+
+    pub const fn from_color(color: Color) -> Self {
+        let a = color.a() as u32;
+        Self::from_rgba(
+            mul(color.r() as u32, a) as u8,
+            mul(color.g() as u32, a) as u8,
+            mul(color.b() as u32, a) as u8,
+            a as u8,
+        )
+    }
+
+    pub const fn to_color(self) -> Color {
+        let a = self.a() as u32;
+        if a == 0 {
+            return Color::from_u32(0);
+        }
+        if a == 0xFF {
+            return Color::from_u32(self.0);
+        }
+        Color::from_rgba(
+            div(self.r() as u32, a),
+            div(self.g() as u32, a),
+            div(self.b() as u32, a),
+            a as u8,
+        )
+    }
+
+    pub const fn modulate(self, other: Self) -> Self {
+        Self::from_rgba(
+            mul(self.r() as u32, other.r() as u32) as u8,
+            mul(self.g() as u32, other.g() as u32) as u8,
+            mul(self.b() as u32, other.b() as u32) as u8,
+            mul(self.a() as u32, other.a() as u32) as u8,
+        )
+    }
+
+    // Moves from `self` towards `other` by `t / 255`.
+    pub const fn lerp(self, other: Self, t: u8) -> Self {
+        let (s, t) = (255 - t as u32, t as u32);
+        Self::from_rgba(
+            (mul(self.r() as u32, s) + mul(other.r() as u32, t)) as u8,
+            (mul(self.g() as u32, s) + mul(other.g() as u32, t)) as u8,
+            (mul(self.b() as u32, s) + mul(other.b() as u32, t)) as u8,
+            (mul(self.a() as u32, s) + mul(other.a() as u32, t)) as u8,
+        )
+    }
+
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_u32(v: u32) -> Self {
+        Self(v)
+    }
+
+    pub const fn a(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub const fn r(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    pub const fn g(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub const fn b(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+impl From<Color> for PremulColor {
+    fn from(color: Color) -> Self {
+        Self::from_color(color)
+    }
+}
+
+impl From<PremulColor> for Color {
+    fn from(color: PremulColor) -> Self {
+        color.to_color()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_color() {
+        let color = PremulColor::from_color(Color::from_rgba(0xFF, 0x80, 0x40, 0x80));
+        assert_eq!(color.as_u32(), 0x80804020);
+        assert_eq!(
+            (color.r(), color.g(), color.b(), color.a()),
+            (0x80, 0x40, 0x20, 0x80)
+        );
+        // Premultiplied channels never exceed alpha.
+        for a in 0..=255 {
+            for c in 0..=255 {
+                let color = PremulColor::from_color(Color::from_rgba(c, 0, 255 - c, a));
+                assert!(color.r() <= a && color.b() <= a);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_at_alpha_extremes() {
+        for c in 0..=255 {
+            let color = Color::from_rgba(c, 255 - c, c / 2, 0xFF);
+            let premul = PremulColor::from_color(color);
+            assert_eq!(premul.as_u32(), color.as_u32());
+            assert_eq!(premul.to_color(), color);
+
+            let color = color.with_a(0x00);
+            let premul = PremulColor::from_color(color);
+            assert_eq!(premul.as_u32(), 0);
+            assert_eq!(premul.to_color(), Color::TRANSPARENT);
+        }
+    }
+
+    #[test]
+    fn round_trip_error_shrinks_with_alpha() {
+        for a in 1..=255u8 {
+            // Premultiplying rounds to a step of `255 / a` straight values.
+            let bound = 127.5 / a as f32 + 0.5;
+            for c in 0..=255u8 {
+                let color = Color::from_rgba(c, c, c, a);
+                let back = PremulColor::from_color(color).to_color();
+                assert_eq!(back.a(), a);
+                assert!((back.r() as f32 - c as f32).abs() <= bound, "{c} at {a}");
+            }
+        }
+    }
+
+    #[test]
+    fn modulate_and_lerp() {
+        let color = PremulColor::from_rgba(0x40, 0x60, 0x80, 0x80);
+        let half = PremulColor::from_rgba(0x80, 0x80, 0x80, 0x80);
+        assert_eq!(color.modulate(half).as_u32(), 0x40203040);
+        let other = PremulColor::from_rgba(0x00, 0x10, 0xFF, 0xFF);
+        assert_eq!(color.lerp(other, 0).as_u32(), color.as_u32());
+        assert_eq!(color.lerp(other, 255).as_u32(), other.as_u32());
+        let halfway = PremulColor::TRANSPARENT.lerp(half, 0x80);
+        assert_eq!(halfway.as_u32(), 0x40404040);
+    }
+}
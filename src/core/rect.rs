@@ -0,0 +1,124 @@
+use crate::core::*;
+
+// An integer rectangle. A negative size extends the rectangle to the left or
+// top of `pos`; blits treat such rectangles as mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub pos: IVec2,
+    pub size: IVec2,
+}
+
+impl Rect {
+    pub const ZERO: Self = Self::new(IVec2::ZERO, IVec2::ZERO);
+
+    pub const fn new(pos: IVec2, size: IVec2) -> Self {
+        Self { pos, size }
+    }
+
+    pub const fn from_size(size: IVec2) -> Self {
+        Self::new(IVec2::ZERO, size)
+    }
+
+    pub fn from_min_max(min: IVec2, max: IVec2) -> Self {
+        Self::new(min, max - min)
+    }
+
+    pub fn min(self) -> IVec2 {
+        self.pos
+    }
+
+    pub fn max(self) -> IVec2 {
+        self.pos + self.size
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.size.x <= 0 || self.size.y <= 0
+    }
+
+    pub fn contains(self, pos: IVec2) -> bool {
+        pos.cmpge(self.min()).all() && pos.cmplt(self.max()).all()
+    }
+
+    // The same area with a non-negative size.
+    pub fn abs(self) -> Self {
+        let min = self.min().min(self.max());
+        Self::new(min, self.size.abs())
+    }
+
+    // The overlapping area, with a zero size when there is none.
+    pub fn intersect(self, other: Self) -> Self {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max()).max(min);
+        Self::from_min_max(min, max)
+    }
+
+    pub fn translate(self, offset: IVec2) -> Self {
+        Self::new(self.pos + offset, self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_max_and_contains() {
+        let rect = Rect::from_min_max(ivec2(1, 2), ivec2(4, 6));
+        assert_eq!(rect, Rect::new(ivec2(1, 2), ivec2(3, 4)));
+        assert!(rect.contains(ivec2(1, 2)));
+        assert!(rect.contains(ivec2(3, 5)));
+        assert!(!rect.contains(ivec2(4, 5)));
+        assert!(!rect.contains(ivec2(3, 6)));
+        assert!(!rect.contains(ivec2(0, 2)));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(Rect::ZERO.is_empty());
+        assert!(Rect::from_size(ivec2(0, 5)).is_empty());
+        assert!(Rect::from_size(ivec2(5, 0)).is_empty());
+        assert!(Rect::from_size(ivec2(-5, 5)).is_empty());
+        assert!(!Rect::from_size(ivec2(1, 1)).is_empty());
+        assert!(!Rect::ZERO.contains(IVec2::ZERO));
+    }
+
+    #[test]
+    fn abs_reaches_back_from_pos() {
+        let rect = Rect::new(ivec2(4, 3), ivec2(-4, -2));
+        assert_eq!(rect.abs(), Rect::new(ivec2(0, 1), ivec2(4, 2)));
+        let rect = Rect::new(ivec2(4, 3), ivec2(-4, 2));
+        assert_eq!(rect.abs(), Rect::new(ivec2(0, 3), ivec2(4, 2)));
+        assert_eq!(rect.abs().abs(), rect.abs());
+    }
+
+    #[test]
+    fn intersect_overlapping() {
+        let a = Rect::new(ivec2(0, 0), ivec2(10, 10));
+        let b = Rect::new(ivec2(5, -3), ivec2(10, 6));
+        assert_eq!(a.intersect(b), Rect::new(ivec2(5, 0), ivec2(5, 3)));
+        assert_eq!(b.intersect(a), a.intersect(b));
+        let inner = Rect::new(ivec2(2, 2), ivec2(3, 3));
+        assert_eq!(a.intersect(inner), inner);
+    }
+
+    #[test]
+    fn intersect_disjoint() {
+        let a = Rect::new(ivec2(0, 0), ivec2(10, 10));
+        for b in [
+            Rect::new(ivec2(10, 0), ivec2(5, 5)),
+            Rect::new(ivec2(0, 10), ivec2(5, 5)),
+            Rect::new(ivec2(-5, -5), ivec2(5, 5)),
+            Rect::new(ivec2(20, 20), ivec2(5, 5)),
+        ] {
+            let clip = a.intersect(b);
+            assert!(clip.is_empty());
+            assert!(clip.size.cmpge(IVec2::ZERO).all());
+        }
+    }
+
+    #[test]
+    fn translate() {
+        let rect = Rect::new(ivec2(1, 2), ivec2(3, 4)).translate(ivec2(-3, 5));
+        assert_eq!(rect, Rect::new(ivec2(-2, 7), ivec2(3, 4)));
+    }
+}
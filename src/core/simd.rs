@@ -0,0 +1,408 @@
+// Kernels over spans of packed ARGB pixels. On x86_64 they use AVX2 when the
+// CPU has it and SSE2 otherwise; elsewhere they fall back to the scalar code
+// in `scalar`, which they match bit for bit.
+
+// Sets every pixel of `dst` to `value`.
+pub(crate) fn fill(dst: &mut [u32], value: u32) {
+    Level::detect().fill(dst, value);
+}
+
+// Copies `src` over the start of `dst`.
+pub(crate) fn copy(dst: &mut [u32], src: &[u32]) {
+    Level::detect().copy(dst, src);
+}
+
+// Draws premultiplied `src` over premultiplied `dst` (`BlendMode::SourceOver`).
+pub(crate) fn blend_over(dst: &mut [u32], src: &[u32]) {
+    Level::detect().blend_over(dst, src);
+}
+
+// Writes `src` modulated by `tint` channel by channel (`Color::modulate`).
+pub(crate) fn tint(dst: &mut [u32], src: &[u32], tint: u32) {
+    Level::detect().tint(dst, src, tint);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Level {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                Self::Avx2
+            } else {
+                Self::Sse2
+            }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Self::Scalar
+    }
+
+    // The SIMD levels only exist on x86_64, where SSE2 is always available,
+    // and AVX2 is only picked after detecting it.
+    fn fill(self, dst: &mut [u32], value: u32) {
+        match self {
+            Self::Scalar => scalar::fill(dst, value),
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => unsafe { x86::fill_sse2(dst, value) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { x86::fill_avx2(dst, value) },
+        }
+    }
+
+    fn copy(self, dst: &mut [u32], src: &[u32]) {
+        let len = dst.len().min(src.len());
+        let (dst, src) = (&mut dst[..len], &src[..len]);
+        match self {
+            Self::Scalar => scalar::copy(dst, src),
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => unsafe { x86::copy_sse2(dst, src) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { x86::copy_avx2(dst, src) },
+        }
+    }
+
+    fn blend_over(self, dst: &mut [u32], src: &[u32]) {
+        let len = dst.len().min(src.len());
+        let (dst, src) = (&mut dst[..len], &src[..len]);
+        match self {
+            Self::Scalar => scalar::blend_over(dst, src),
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => unsafe { x86::blend_over_sse2(dst, src) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { x86::blend_over_avx2(dst, src) },
+        }
+    }
+
+    fn tint(self, dst: &mut [u32], src: &[u32], tint: u32) {
+        let len = dst.len().min(src.len());
+        let (dst, src) = (&mut dst[..len], &src[..len]);
+        match self {
+            Self::Scalar => scalar::tint(dst, src, tint),
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => unsafe { x86::tint_sse2(dst, src, tint) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { x86::tint_avx2(dst, src, tint) },
+        }
+    }
+}
+
+mod scalar {
+    use crate::core::*;
+
+    pub fn fill(dst: &mut [u32], value: u32) {
+        dst.fill(value);
+    }
+
+    pub fn copy(dst: &mut [u32], src: &[u32]) {
+        dst.copy_from_slice(src);
+    }
+
+    pub fn blend_over(dst: &mut [u32], src: &[u32]) {
+        for (dst, &src) in dst.iter_mut().zip(src) {
+            *dst = BlendMode::SourceOver
+                .blend_premul(PremulColor::from_u32(*dst), PremulColor::from_u32(src))
+                .as_u32();
+        }
+    }
+
+    pub fn tint(dst: &mut [u32], src: &[u32], tint: u32) {
+        let tint = Color::from_u32(tint);
+        for (dst, &src) in dst.iter_mut().zip(src) {
+            *dst = Color::from_u32(src).modulate(tint).as_u32();
+        }
+    }
+}
+
+// Every function takes slices of equal length and handles the pixels that
+// don't fill a whole vector with the scalar code. Pixels are widened to 16
+// bits per channel, where `mul` from `premul` fits without overflowing.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::scalar;
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn fill_sse2(dst: &mut [u32], value: u32) {
+        let value_x4 = _mm_set1_epi32(value as i32);
+        let mut dst = dst.chunks_exact_mut(4);
+        for chunk in &mut dst {
+            unsafe { _mm_storeu_si128(chunk.as_mut_ptr().cast(), value_x4) };
+        }
+        scalar::fill(dst.into_remainder(), value);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn fill_avx2(dst: &mut [u32], value: u32) {
+        let value_x8 = _mm256_set1_epi32(value as i32);
+        let mut dst = dst.chunks_exact_mut(8);
+        for chunk in &mut dst {
+            unsafe { _mm256_storeu_si256(chunk.as_mut_ptr().cast(), value_x8) };
+        }
+        scalar::fill(dst.into_remainder(), value);
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn copy_sse2(dst: &mut [u32], src: &[u32]) {
+        let mut dst = dst.chunks_exact_mut(4);
+        let mut src = src.chunks_exact(4);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let pixels = _mm_loadu_si128(src.as_ptr().cast());
+                _mm_storeu_si128(dst.as_mut_ptr().cast(), pixels);
+            }
+        }
+        scalar::copy(dst.into_remainder(), src.remainder());
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn copy_avx2(dst: &mut [u32], src: &[u32]) {
+        let mut dst = dst.chunks_exact_mut(8);
+        let mut src = src.chunks_exact(8);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let pixels = _mm256_loadu_si256(src.as_ptr().cast());
+                _mm256_storeu_si256(dst.as_mut_ptr().cast(), pixels);
+            }
+        }
+        scalar::copy(dst.into_remainder(), src.remainder());
+    }
+
+    // `mul` on every 16-bit lane.
+    #[target_feature(enable = "sse2")]
+    fn mul_sse2(a: __m128i, b: __m128i) -> __m128i {
+        let x = _mm_add_epi16(_mm_mullo_epi16(a, b), _mm_set1_epi16(128));
+        _mm_srli_epi16(_mm_add_epi16(x, _mm_srli_epi16(x, 8)), 8)
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn mul_avx2(a: __m256i, b: __m256i) -> __m256i {
+        let x = _mm256_add_epi16(_mm256_mullo_epi16(a, b), _mm256_set1_epi16(128));
+        _mm256_srli_epi16(_mm256_add_epi16(x, _mm256_srli_epi16(x, 8)), 8)
+    }
+
+    // `src + dst * (255 - src alpha)` on two widened pixels.
+    #[target_feature(enable = "sse2")]
+    fn over_sse2(dst: __m128i, src: __m128i) -> __m128i {
+        let alpha = _mm_shufflehi_epi16(_mm_shufflelo_epi16(src, 0xFF), 0xFF);
+        let inverse = _mm_sub_epi16(_mm_set1_epi16(255), alpha);
+        _mm_add_epi16(src, mul_sse2(dst, inverse))
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn over_avx2(dst: __m256i, src: __m256i) -> __m256i {
+        let alpha = _mm256_shufflehi_epi16(_mm256_shufflelo_epi16(src, 0xFF), 0xFF);
+        let inverse = _mm256_sub_epi16(_mm256_set1_epi16(255), alpha);
+        _mm256_add_epi16(src, mul_avx2(dst, inverse))
+    }
+
+    // Same as `BlendMode::blend_premul` for valid premultiplied colors, where
+    // no channel exceeds alpha and the result needs no clamping.
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn blend_over_sse2(dst: &mut [u32], src: &[u32]) {
+        let zero = _mm_setzero_si128();
+        let mut dst = dst.chunks_exact_mut(4);
+        let mut src = src.chunks_exact(4);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let s = _mm_loadu_si128(src.as_ptr().cast());
+                let d = _mm_loadu_si128(dst.as_ptr().cast());
+                let lo = over_sse2(_mm_unpacklo_epi8(d, zero), _mm_unpacklo_epi8(s, zero));
+                let hi = over_sse2(_mm_unpackhi_epi8(d, zero), _mm_unpackhi_epi8(s, zero));
+                _mm_storeu_si128(dst.as_mut_ptr().cast(), _mm_packus_epi16(lo, hi));
+            }
+        }
+        scalar::blend_over(dst.into_remainder(), src.remainder());
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn blend_over_avx2(dst: &mut [u32], src: &[u32]) {
+        let zero = _mm256_setzero_si256();
+        let mut dst = dst.chunks_exact_mut(8);
+        let mut src = src.chunks_exact(8);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let s = _mm256_loadu_si256(src.as_ptr().cast());
+                let d = _mm256_loadu_si256(dst.as_ptr().cast());
+                // Unpacking and packing both work within 128-bit halves, so
+                // the pixels come back in their original order.
+                let lo = over_avx2(_mm256_unpacklo_epi8(d, zero), _mm256_unpacklo_epi8(s, zero));
+                let hi = over_avx2(_mm256_unpackhi_epi8(d, zero), _mm256_unpackhi_epi8(s, zero));
+                _mm256_storeu_si256(dst.as_mut_ptr().cast(), _mm256_packus_epi16(lo, hi));
+            }
+        }
+        scalar::blend_over(dst.into_remainder(), src.remainder());
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn tint_sse2(dst: &mut [u32], src: &[u32], tint: u32) {
+        let zero = _mm_setzero_si128();
+        let tint_x2 = _mm_unpacklo_epi8(_mm_set1_epi32(tint as i32), zero);
+        let mut dst = dst.chunks_exact_mut(4);
+        let mut src = src.chunks_exact(4);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let s = _mm_loadu_si128(src.as_ptr().cast());
+                let lo = mul_sse2(_mm_unpacklo_epi8(s, zero), tint_x2);
+                let hi = mul_sse2(_mm_unpackhi_epi8(s, zero), tint_x2);
+                _mm_storeu_si128(dst.as_mut_ptr().cast(), _mm_packus_epi16(lo, hi));
+            }
+        }
+        scalar::tint(dst.into_remainder(), src.remainder(), tint);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn tint_avx2(dst: &mut [u32], src: &[u32], tint: u32) {
+        let zero = _mm256_setzero_si256();
+        let tint_x4 = _mm256_unpacklo_epi8(_mm256_set1_epi32(tint as i32), zero);
+        let mut dst = dst.chunks_exact_mut(8);
+        let mut src = src.chunks_exact(8);
+        for (dst, src) in (&mut dst).zip(&mut src) {
+            unsafe {
+                let s = _mm256_loadu_si256(src.as_ptr().cast());
+                let lo = mul_avx2(_mm256_unpacklo_epi8(s, zero), tint_x4);
+                let hi = mul_avx2(_mm256_unpackhi_epi8(s, zero), tint_x4);
+                _mm256_storeu_si256(dst.as_mut_ptr().cast(), _mm256_packus_epi16(lo, hi));
+            }
+        }
+        scalar::tint(dst.into_remainder(), src.remainder(), tint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::*;
+
+    fn levels() -> Vec<Level> {
+        let mut levels = vec![Level::Scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            levels.push(Level::Sse2);
+            if is_x86_feature_detected!("avx2") {
+                levels.push(Level::Avx2);
+            }
+        }
+        levels
+    }
+
+    // Deterministic pseudo-random pixels from a xorshift generator.
+    fn random(seed: u32, len: usize) -> Vec<u32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state
+            })
+            .collect()
+    }
+
+    // Valid premultiplied pixels, including fully transparent and opaque ones.
+    fn random_premul(seed: u32, len: usize) -> Vec<u32> {
+        random(seed, len)
+            .into_iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let color = Color::from_u32(pixel);
+                let color = match i % 5 {
+                    0 => color.with_a(0x00),
+                    1 => color.with_a(0xFF),
+                    _ => color,
+                };
+                PremulColor::from_color(color).as_u32()
+            })
+            .collect()
+    }
+
+    // Lengths around every vector width, so the remainders are covered too.
+    const LENGTHS: [usize; 9] = [0, 1, 3, 4, 7, 8, 9, 31, 200];
+
+    #[test]
+    fn fill_matches_scalar() {
+        for level in levels() {
+            for len in LENGTHS {
+                let mut dst = random(1, len);
+                let mut expected = dst.clone();
+                scalar::fill(&mut expected, 0x80FF4020);
+                level.fill(&mut dst, 0x80FF4020);
+                assert_eq!(dst, expected, "{level:?} {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn copy_matches_scalar() {
+        for level in levels() {
+            for len in LENGTHS {
+                let src = random(2, len);
+                let mut dst = random(3, len + 2);
+                let mut expected = dst.clone();
+                scalar::copy(&mut expected[..len], &src);
+                level.copy(&mut dst, &src);
+                assert_eq!(dst, expected, "{level:?} {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn blend_over_matches_scalar() {
+        for level in levels() {
+            for len in LENGTHS {
+                let src = random_premul(4, len);
+                let mut dst = random_premul(5, len);
+                let mut expected = dst.clone();
+                scalar::blend_over(&mut expected, &src);
+                level.blend_over(&mut dst, &src);
+                assert_eq!(dst, expected, "{level:?} {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn blend_over_all_alphas() {
+        // Every source alpha over every destination alpha, with channels at
+        // both ends of their range.
+        let mut src = Vec::new();
+        let mut dst = Vec::new();
+        for sa in 0..=255 {
+            for da in 0..=255 {
+                src.push(PremulColor::from_rgba(sa, sa / 2, 0, sa).as_u32());
+                dst.push(PremulColor::from_rgba(0, da, da / 3, da).as_u32());
+            }
+        }
+        let mut expected = dst.clone();
+        scalar::blend_over(&mut expected, &src);
+        for level in levels() {
+            let mut dst = dst.clone();
+            level.blend_over(&mut dst, &src);
+            assert_eq!(dst, expected, "{level:?}");
+        }
+    }
+
+    #[test]
+    fn tint_matches_scalar() {
+        for level in levels() {
+            for len in LENGTHS {
+                for tint in random(6, 8).into_iter().chain([0, u32::MAX]) {
+                    let src = random(7, len);
+                    let mut dst = vec![0; len];
+                    let mut expected = dst.clone();
+                    scalar::tint(&mut expected, &src, tint);
+                    level.tint(&mut dst, &src, tint);
+                    assert_eq!(dst, expected, "{level:?} {len} {tint:08X}");
+                }
+            }
+        }
+    }
+}
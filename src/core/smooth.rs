@@ -0,0 +1,315 @@
+use crate::core::*;
+
+pub(crate) fn put_coverage<S>(surface: &mut S, pos: IVec2, color: Color, coverage: f32)
+where
+    S: Surface + ?Sized,
+{
+    if coverage <= 0.0 || !surface.clip_rect().contains(pos) {
+        return;
+    }
+    let alpha = (color.a() as f32 * coverage.min(1.0) + 0.5) as u8;
+    surface.blend_pixel(pos, color.with_a(alpha), BlendMode::SourceOver);
+}
+
+// Visits every pixel whose center lies within the clipped bounding box.
+fn for_each_pixel<S, F>(surface: &mut S, min: Vec2, max: Vec2, color: Color, mut coverage: F)
+where
+    S: Surface + ?Sized,
+    F: FnMut(Vec2) -> f32,
+{
+    let clip = surface.clip_rect();
+    let x0 = (min.x.floor() as i32).max(clip.min().x);
+    let y0 = (min.y.floor() as i32).max(clip.min().y);
+    let x1 = (max.x.ceil() as i32).min(clip.max().x);
+    let y1 = (max.y.ceil() as i32).min(clip.max().y);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let center = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            put_coverage(surface, ivec2(x, y), color, coverage(center));
+        }
+    }
+}
+
+fn fract(v: f32) -> f32 {
+    v - v.floor()
+}
+
+pub fn draw_line_aa<S>(surface: &mut S, a: Vec2, b: Vec2, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    // Xiaolin Wu's algorithm on pixel-center coordinates.
+    let (mut a, mut b) = (a - 0.5, b - 0.5);
+    let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+    if steep {
+        a = vec2(a.y, a.x);
+        b = vec2(b.y, b.x);
+    }
+    if a.x > b.x {
+        std::mem::swap(&mut a, &mut b);
+    }
+    let dx = b.x - a.x;
+    let gradient = if dx == 0.0 { 1.0 } else { (b.y - a.y) / dx };
+
+    // Coordinates are checked against the clip while still floats, so ones
+    // beyond the i32 range are dropped instead of saturating.
+    let clip = surface.clip_rect();
+    let (min, max) = (clip.min().as_vec2(), clip.max().as_vec2());
+    let mut plot = |x: f32, y: f32, coverage: f32| {
+        let pos = if steep { vec2(y, x) } else { vec2(x, y) };
+        if pos.cmpge(min).all() && pos.cmplt(max).all() {
+            put_coverage(surface, pos.as_ivec2(), color, coverage);
+        }
+    };
+
+    let x_start = a.x.round();
+    let y_start = a.y + gradient * (x_start - a.x);
+    let x_gap = 1.0 - fract(a.x + 0.5);
+    let y = y_start.floor();
+    plot(x_start, y, (1.0 - (y_start - y)) * x_gap);
+    plot(x_start, y + 1.0, (y_start - y) * x_gap);
+
+    let x_stop = b.x.round();
+    let y_end = b.y + gradient * (x_stop - b.x);
+    let x_gap = fract(b.x + 0.5);
+    let y = y_end.floor();
+    plot(x_stop, y, (1.0 - (y_end - y)) * x_gap);
+    plot(x_stop, y + 1.0, (y_end - y) * x_gap);
+
+    // Only step through the columns inside the clip rectangle, starting from
+    // where the line enters it.
+    let (clip_min, clip_max) = if steep {
+        (min.y, max.y)
+    } else {
+        (min.x, max.x)
+    };
+    let first = (x_start + 1.0).max(clip_min);
+    let last = (x_stop - 1.0).min(clip_max - 1.0);
+    let mut inter_y = y_start + gradient * (first - x_start);
+    for x in first as i32..=last as i32 {
+        let y = inter_y.floor();
+        plot(x as f32, y, 1.0 - (inter_y - y));
+        plot(x as f32, y + 1.0, inter_y - y);
+        inter_y += gradient;
+    }
+}
+
+pub fn fill_circle_aa<S>(surface: &mut S, center: Vec2, radius: f32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let extent = Vec2::splat(radius + 1.0);
+    for_each_pixel(surface, center - extent, center + extent, color, |p| {
+        (radius + 0.5 - p.distance(center)).clamp(0.0, 1.0)
+    });
+}
+
+pub fn draw_circle_aa<S>(surface: &mut S, center: Vec2, radius: f32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let extent = Vec2::splat(radius + 1.0);
+    for_each_pixel(surface, center - extent, center + extent, color, |p| {
+        (1.0 - (p.distance(center) - radius).abs()).clamp(0.0, 1.0)
+    });
+}
+
+fn rounded_rect_distance(p: Vec2, center: Vec2, half: Vec2, radius: f32) -> f32 {
+    let q = (p - center).abs() - half + radius;
+    q.max(Vec2::ZERO).length() + q.x.max(q.y).min(0.0) - radius
+}
+
+pub fn fill_rounded_rect_aa<S>(surface: &mut S, pos: Vec2, size: Vec2, radius: f32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    let half = size / 2.0;
+    let center = pos + half;
+    let radius = radius.clamp(0.0, half.min_element());
+    for_each_pixel(surface, pos - 1.0, pos + size + 1.0, color, |p| {
+        (0.5 - rounded_rect_distance(p, center, half, radius)).clamp(0.0, 1.0)
+    });
+}
+
+pub fn draw_rounded_rect_aa<S>(surface: &mut S, pos: Vec2, size: Vec2, radius: f32, color: Color)
+where
+    S: Surface + ?Sized,
+{
+    // The outline is one pixel wide and lies just inside the rectangle.
+    let half = size / 2.0 - 0.5;
+    let center = pos + size / 2.0;
+    let radius = (radius - 0.5).clamp(0.0, half.min_element().max(0.0));
+    for_each_pixel(surface, pos - 1.0, pos + size + 1.0, color, |p| {
+        (1.0 - rounded_rect_distance(p, center, half, radius).abs()).clamp(0.0, 1.0)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    const BACKGROUND: Color = Color::BLACK;
+
+    fn draw<F>(clip: Option<Rect>, f: F) -> Image
+    where
+        F: FnOnce(&mut Image),
+    {
+        let mut image = Image::new(32, 32, BACKGROUND);
+        if let Some(clip) = clip {
+            image.push_clip(clip);
+        }
+        f(&mut image);
+        image
+    }
+
+    // Drawing with `clip` pushed changes exactly the pixels inside it that
+    // drawing without a clip changes.
+    fn assert_clips<F>(clip: Rect, f: F)
+    where
+        F: Fn(&mut Image),
+    {
+        let full = draw(None, &f);
+        let clipped = draw(Some(clip), &f);
+        for y in 0..32 {
+            for x in 0..32 {
+                let pos = ivec2(x, y);
+                let expected = if clip.contains(pos) {
+                    full.get_pixel(pos)
+                } else {
+                    BACKGROUND
+                };
+                assert_eq!(clipped.get_pixel(pos), expected, "{pos}");
+            }
+        }
+    }
+
+    fn is_symmetric(image: &Image) -> bool {
+        (0..32).all(|y| {
+            (0..32).all(|x| {
+                let pixel = image.get_pixel(ivec2(x, y));
+                pixel == image.get_pixel(ivec2(31 - x, y))
+                    && pixel == image.get_pixel(ivec2(x, 31 - y))
+            })
+        })
+    }
+
+    #[test]
+    fn clipped_lines_match_unclipped() {
+        let clip = Rect::new(ivec2(7, 5), ivec2(20, 9));
+        // Slopes that are exact in binary, so stepping from the clip edge
+        // lands on the same values as stepping from the start.
+        for (a, b) in [
+            (vec2(0.5, 0.5), vec2(40.5, 20.5)),
+            (vec2(30.5, 2.5), vec2(1.5, 9.75)),
+            (vec2(12.5, -4.5), vec2(16.5, 35.5)),
+            (vec2(3.25, 25.5), vec2(28.75, 25.5)),
+        ] {
+            assert_clips(clip, |image| image.draw_line_aa(a, b, Color::WHITE));
+        }
+    }
+
+    #[test]
+    fn off_screen_lines() {
+        let image = draw(None, |image| {
+            image.draw_line_aa(vec2(-1e30, 5.5), vec2(1e30, 5.5), Color::WHITE);
+        });
+        for y in 0..32 {
+            let expected = if y == 5 { Color::WHITE } else { BACKGROUND };
+            assert!((0..32).all(|x| image.get_pixel(ivec2(x, y)) == expected));
+        }
+
+        let image = draw(None, |image| {
+            image.draw_line_aa(vec2(-1e9, -1e9), vec2(1e9, 1e9), Color::WHITE);
+        });
+        for i in 0..32 {
+            assert_eq!(image.get_pixel(ivec2(i, i)), Color::WHITE);
+        }
+
+        for (a, b) in [
+            (vec2(-100.0, -100.0), vec2(-1e9, 50.0)),
+            (vec2(40.0, 1e20), vec2(40.0, -1e20)),
+            (vec2(f32::NAN, 3.0), vec2(10.0, 3.0)),
+            (vec2(f32::MIN, f32::MAX), vec2(f32::MAX, f32::MIN)),
+        ] {
+            let clip = Rect::new(ivec2(2, 2), ivec2(4, 4));
+            let image = draw(Some(clip), |image| image.draw_line_aa(a, b, Color::WHITE));
+            for y in 0..32 {
+                for x in 0..32 {
+                    if !clip.contains(ivec2(x, y)) {
+                        assert_eq!(image.get_pixel(ivec2(x, y)), BACKGROUND);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn circles() {
+        let center = vec2(16.0, 16.0);
+        let filled = draw(None, |image| {
+            image.fill_circle_aa(center, 6.0, Color::WHITE)
+        });
+        assert!(is_symmetric(&filled));
+        assert_eq!(filled.get_pixel(ivec2(16, 16)), Color::WHITE);
+        assert_eq!(filled.get_pixel(ivec2(20, 16)), Color::WHITE);
+        assert_eq!(filled.get_pixel(ivec2(23, 16)), BACKGROUND);
+        // The rim is partially covered.
+        let rim = filled.get_pixel(ivec2(20, 20));
+        assert!(rim != Color::WHITE && rim != BACKGROUND);
+
+        let outline = draw(None, |image| {
+            image.draw_circle_aa(center, 6.0, Color::WHITE)
+        });
+        assert!(is_symmetric(&outline));
+        assert_eq!(outline.get_pixel(ivec2(16, 16)), BACKGROUND);
+        assert_ne!(outline.get_pixel(ivec2(10, 15)), BACKGROUND);
+        assert_eq!(outline.get_pixel(ivec2(25, 15)), BACKGROUND);
+
+        let clip = Rect::new(ivec2(12, 3), ivec2(9, 10));
+        assert_clips(clip, |image| {
+            image.fill_circle_aa(center, 6.0, Color::WHITE)
+        });
+        assert_clips(clip, |image| {
+            image.draw_circle_aa(center, 6.0, Color::WHITE)
+        });
+    }
+
+    #[test]
+    fn rounded_rects() {
+        let (pos, size) = (vec2(4.0, 4.0), vec2(24.0, 24.0));
+        let square = draw(None, |image| {
+            image.fill_rounded_rect_aa(pos, size, 0.0, Color::WHITE)
+        });
+        let rect = draw(None, |image| {
+            image.fill_rect(ivec2(4, 4), ivec2(24, 24), Color::WHITE)
+        });
+        assert_eq!(square.pixels(), rect.pixels());
+
+        let filled = draw(None, |image| {
+            image.fill_rounded_rect_aa(pos, size, 6.0, Color::WHITE)
+        });
+        assert!(is_symmetric(&filled));
+        assert_eq!(filled.get_pixel(ivec2(4, 4)), BACKGROUND);
+        assert_eq!(filled.get_pixel(ivec2(4, 16)), Color::WHITE);
+        assert_eq!(filled.get_pixel(ivec2(16, 16)), Color::WHITE);
+        let corner = filled.get_pixel(ivec2(5, 5));
+        assert!(corner != Color::WHITE && corner != BACKGROUND);
+
+        let outline = draw(None, |image| {
+            image.draw_rounded_rect_aa(pos, size, 6.0, Color::WHITE)
+        });
+        assert!(is_symmetric(&outline));
+        assert_eq!(outline.get_pixel(ivec2(16, 4)), Color::WHITE);
+        assert_eq!(outline.get_pixel(ivec2(16, 16)), BACKGROUND);
+        assert_eq!(outline.get_pixel(ivec2(4, 4)), BACKGROUND);
+
+        let clip = Rect::new(ivec2(0, 10), ivec2(9, 30));
+        assert_clips(clip, |image| {
+            image.fill_rounded_rect_aa(pos, size, 6.0, Color::WHITE)
+        });
+        assert_clips(clip, |image| {
+            image.draw_rounded_rect_aa(pos, size, 6.0, Color::WHITE)
+        });
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::LazyLock;
+
+use crate::core::*;
+
+const ENCODE_STEPS: usize = 4096;
+
+static DECODE: LazyLock<[f32; 256]> = LazyLock::new(|| {
+    let mut table = [0.0; 256];
+    for (i, v) in table.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        *v = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    table
+});
+
+static ENCODE: LazyLock<[u8; ENCODE_STEPS]> = LazyLock::new(|| {
+    let mut table = [0; ENCODE_STEPS];
+    for (i, v) in table.iter_mut().enumerate() {
+        let l = i as f32 / (ENCODE_STEPS - 1) as f32;
+        *v = (encode(l) * 255.0 + 0.5) as u8;
+    }
+    table
+});
+
+// Exact sRGB encoding, for callers that need more than 8 bits of output.
+pub(crate) fn encode(l: f32) -> f32 {
+    if l <= 0.0031308 {
+        l * 12.92
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(v: u8) -> f32 {
+    DECODE[v as usize]
+}
+
+pub fn linear_to_srgb(v: f32) -> u8 {
+    ENCODE[(v.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32 + 0.5) as usize]
+}
+
+impl Color {
+    pub fn to_linear(self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.r()),
+            srgb_to_linear(self.g()),
+            srgb_to_linear(self.b()),
+            self.a() as f32 / 255.0,
+        ]
+    }
+
+    pub fn from_linear(rgba: [f32; 4]) -> Self {
+        Self::from_rgba(
+            linear_to_srgb(rgba[0]),
+            linear_to_srgb(rgba[1]),
+            linear_to_srgb(rgba[2]),
+            (rgba[3].clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+        )
+    }
+
+    pub fn lerp_linear(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let a = premultiply(self.to_linear());
+        let b = premultiply(other.to_linear());
+        let c = std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+        Self::from_linear(unpremultiply(c))
+    }
+}
+
+pub(crate) fn premultiply(c: [f32; 4]) -> [f32; 4] {
+    [c[0] * c[3], c[1] * c[3], c[2] * c[3], c[3]]
+}
+
+pub(crate) fn unpremultiply(c: [f32; 4]) -> [f32; 4] {
+    if c[3] <= 0.0 {
+        return [0.0; 4];
+    }
+    [c[0] / c[3], c[1] / c[3], c[2] / c[3], c[3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    #[test]
+    fn decode_table() {
+        assert_eq!(srgb_to_linear(0), 0.0);
+        assert_eq!(srgb_to_linear(255), 1.0);
+        assert!((srgb_to_linear(128) - 0.21586).abs() < 1e-5);
+        assert!((srgb_to_linear(10) - 10.0 / 255.0 / 12.92).abs() < 1e-7);
+        assert!((1..=255).all(|v| srgb_to_linear(v) > srgb_to_linear(v - 1)));
+    }
+
+    #[test]
+    fn every_value_round_trips() {
+        for v in 0..=255 {
+            let linear = srgb_to_linear(v);
+            assert_eq!(linear_to_srgb(linear), v, "{v}");
+            // The table agrees with the exact encoding to within rounding.
+            assert!((encode(linear) * 255.0 - v as f32).abs() < 1e-3, "{v}");
+        }
+        assert_eq!(linear_to_srgb(-1.0), 0);
+        assert_eq!(linear_to_srgb(2.0), 255);
+    }
+
+    #[test]
+    fn linear_colors_round_trip() {
+        let color = Color::from_rgba(0x12, 0x80, 0xFE, 0x40);
+        assert_eq!(Color::from_linear(color.to_linear()), color);
+        let premul = premultiply(color.to_linear());
+        assert_eq!(Color::from_linear(unpremultiply(premul)), color);
+        assert_eq!(unpremultiply([0.5, 0.5, 0.5, 0.0]), [0.0; 4]);
+    }
+
+    #[test]
+    fn linear_blending() {
+        // Half-covered white over black lands on 0x80 when blended in gamma
+        // space, about a fifth of the light. In linear light it's half.
+        let mut src = Image::new(1, 1, Color::from_rgba(0xFF, 0xFF, 0xFF, 0x80));
+        let half_white = src.get_pixel(IVec2::ZERO);
+        let blended = BlendMode::SourceOver.blend_linear(Color::BLACK, half_white);
+        assert_eq!(blended.as_u32(), 0xFFBCBCBC);
+
+        for (linear, expected) in [(false, 0xFF808080), (true, 0xFFBCBCBC)] {
+            let mut dst = Image::new(1, 1, Color::BLACK);
+            let options = BlitOptions {
+                blend: BlendMode::SourceOver,
+                linear,
+                ..Default::default()
+            };
+            dst.blit_with(&src, None, None, None, None, options);
+            assert_eq!(dst.pixels(), Some(&[expected][..]), "{linear}");
+        }
+
+        // Replace and AlphaTest copy, so there is nothing to linearize.
+        src.premultiply();
+        let mut dst = Image::new(1, 1, Color::BLACK);
+        let options = BlitOptions {
+            blend: BlendMode::Replace,
+            linear: true,
+            ..Default::default()
+        };
+        dst.blit_with(&src, None, None, None, None, options);
+        assert_eq!(dst.get_pixel(IVec2::ZERO), half_white);
+    }
+}
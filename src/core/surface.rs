@@ -1,23 +1,135 @@
 use crate::core::*;
 
+use super::simd;
+
 pub trait Surface {
     fn get_pixel(&self, pos: IVec2) -> Color;
     fn set_pixel(&mut self, pos: IVec2, color: Color);
     fn size(&self) -> IVec2;
 
+    // The area drawing is restricted to, the whole surface by default.
+    fn clip_rect(&self) -> Rect {
+        Rect::from_size(self.size())
+    }
+
+    // Narrows `clip_rect` to `rect` until the matching `pop_clip`. Surfaces
+    // without a clip stack ignore both.
+    fn push_clip(&mut self, _rect: Rect) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn with_clip<R, F>(&mut self, rect: Rect, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+        Self: Sized,
+    {
+        self.push_clip(rect);
+        let result = f(self);
+        self.pop_clip();
+        result
+    }
+
     fn index(pos: IVec2, width: i32) -> i32 {
         pos.y * width + pos.x
     }
 
+    // Contiguous storage of row `y`, in the surface's own pixel format (see
+    // `is_premultiplied`), or `None` if `y` is outside the surface. Surfaces
+    // that can't expose it keep the default.
+    fn row(&self, _y: i32) -> Option<&[u32]> {
+        None
+    }
+
+    fn row_mut(&mut self, _y: i32) -> Option<&mut [u32]> {
+        None
+    }
+
+    // Pixels from the start of one row to the start of the next in `pixels`.
+    fn stride(&self) -> Option<usize> {
+        self.pixels().map(|_| self.size().x as usize)
+    }
+
+    // All rows back to back, `stride()` pixels apart.
+    fn pixels(&self) -> Option<&[u32]> {
+        None
+    }
+
+    fn pixels_mut(&mut self) -> Option<&mut [u32]> {
+        None
+    }
+
     fn clear(&mut self, color: Color) {
-        let size = self.size();
-        for y in 0..size.y {
-            for x in 0..size.x {
+        let clip = self.clip_rect();
+        if clip.is_empty() {
+            return;
+        }
+        let raw = if self.is_premultiplied() {
+            PremulColor::from_color(color).as_u32()
+        } else {
+            color.as_u32()
+        };
+        let (x0, x1) = (clip.min().x, clip.max().x);
+        for y in clip.min().y..clip.max().y {
+            if let Some(row) = self.row_mut(y) {
+                simd::fill(&mut row[x0 as usize..x1 as usize], raw);
+                continue;
+            }
+            for x in x0..x1 {
                 self.set_pixel(ivec2(x, y), color);
             }
         }
     }
 
+    fn is_premultiplied(&self) -> bool {
+        false
+    }
+
+    fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        self.get_pixel(pos).into()
+    }
+
+    fn set_pixel_premul(&mut self, pos: IVec2, color: PremulColor) {
+        self.set_pixel(pos, color.into());
+    }
+
+    fn blend_pixel(&mut self, pos: IVec2, color: Color, mode: BlendMode) {
+        match mode {
+            BlendMode::Replace => self.set_pixel(pos, color),
+            BlendMode::AlphaTest => {
+                if color.a() != 0x00 {
+                    self.set_pixel(pos, color);
+                }
+            }
+            _ => {
+                let dst = self.get_pixel(pos);
+                self.set_pixel(pos, mode.blend(dst, color));
+            }
+        }
+    }
+
+    fn blend_pixel_premul(&mut self, pos: IVec2, color: PremulColor, mode: BlendMode) {
+        match mode {
+            BlendMode::Replace => self.set_pixel_premul(pos, color),
+            BlendMode::AlphaTest => {
+                if color.a() != 0x00 {
+                    self.set_pixel_premul(pos, color);
+                }
+            }
+            _ => {
+                let dst = self.get_pixel_premul(pos);
+                self.set_pixel_premul(pos, mode.blend_premul(dst, color));
+            }
+        }
+    }
+
+    fn view(&self, rect: Rect) -> SubSurface<&Self> {
+        SubSurface::new(self, rect)
+    }
+
+    fn view_mut(&mut self, rect: Rect) -> SubSurface<&mut Self> {
+        SubSurface::new(self, rect)
+    }
+
     fn blit<S>(
         &mut self,
         src: &S,
@@ -29,17 +141,170 @@ pub trait Surface {
         S: Surface,
         Self: Sized,
     {
-        let src_pos = src_pos.unwrap_or(IVec2::ZERO);
-        let src_size = src_size.unwrap_or_else(|| src.size());
-        let dst_pos = dst_pos.unwrap_or(IVec2::ZERO);
-        if let Some(dst_size) = dst_size {
-            if dst_size == src_size {
-                blit::blit_same_size(self, src, src_pos, dst_pos, dst_size);
-            } else {
-                blit::blit_scale(self, src, src_pos, src_size, dst_pos, dst_size);
-            }
-        } else {
-            blit::blit_same_size(self, src, src_pos, dst_pos, src_size);
-        }
+        self.blit_with(
+            src,
+            src_pos,
+            src_size,
+            dst_pos,
+            dst_size,
+            BlitOptions::default(),
+        );
+    }
+
+    // Regions follow `Rect`: a negative size reaches back from the position
+    // and mirrors that axis, so a source at (4, 0) sized (-4, 4) is the first
+    // four columns flipped, while (0, 0) sized (-4, 4) lies outside `src`.
+    fn blit_with<S>(
+        &mut self,
+        src: &S,
+        src_pos: Option<IVec2>,
+        src_size: Option<IVec2>,
+        dst_pos: Option<IVec2>,
+        dst_size: Option<IVec2>,
+        options: BlitOptions,
+    ) where
+        S: Surface,
+        Self: Sized,
+    {
+        let src_rect = Rect::new(
+            src_pos.unwrap_or(IVec2::ZERO),
+            src_size.unwrap_or_else(|| src.size()),
+        );
+        let dst_rect = Rect::new(
+            dst_pos.unwrap_or(IVec2::ZERO),
+            dst_size.unwrap_or(src_rect.size.abs()),
+        );
+        blit::blit(self, src, src_rect, dst_rect, options);
+    }
+
+    fn blit_transform<S>(
+        &mut self,
+        src: &S,
+        src_pos: Option<IVec2>,
+        src_size: Option<IVec2>,
+        transform: Affine2,
+        options: BlitOptions,
+    ) where
+        S: Surface,
+        Self: Sized,
+    {
+        let src_rect = Rect::new(
+            src_pos.unwrap_or(IVec2::ZERO),
+            src_size.unwrap_or_else(|| src.size()),
+        );
+        blit::blit_transform(self, src, src_rect, transform, options);
+    }
+
+    fn draw_line(&mut self, a: IVec2, b: IVec2, color: Color) {
+        draw::draw_line(self, a, b, color);
+    }
+
+    fn draw_thick_line(&mut self, a: IVec2, b: IVec2, width: i32, color: Color) {
+        draw::draw_thick_line(self, a, b, width, color);
+    }
+
+    fn draw_rect(&mut self, pos: IVec2, size: IVec2, color: Color) {
+        draw::draw_rect(self, pos, size, color);
+    }
+
+    fn fill_rect<P>(&mut self, pos: IVec2, size: IVec2, paint: P)
+    where
+        P: Paint,
+    {
+        draw::fill_rect(self, pos, size, paint);
+    }
+
+    fn draw_circle(&mut self, center: IVec2, radius: i32, color: Color) {
+        draw::draw_circle(self, center, radius, color);
+    }
+
+    fn fill_circle(&mut self, center: IVec2, radius: i32, color: Color) {
+        draw::fill_circle(self, center, radius, color);
+    }
+
+    fn draw_ellipse(&mut self, center: IVec2, radii: IVec2, color: Color) {
+        draw::draw_ellipse(self, center, radii, color);
+    }
+
+    fn fill_ellipse(&mut self, center: IVec2, radii: IVec2, color: Color) {
+        draw::fill_ellipse(self, center, radii, color);
+    }
+
+    fn draw_line_aa(&mut self, a: Vec2, b: Vec2, color: Color) {
+        smooth::draw_line_aa(self, a, b, color);
+    }
+
+    fn draw_circle_aa(&mut self, center: Vec2, radius: f32, color: Color) {
+        smooth::draw_circle_aa(self, center, radius, color);
+    }
+
+    fn fill_circle_aa(&mut self, center: Vec2, radius: f32, color: Color) {
+        smooth::fill_circle_aa(self, center, radius, color);
+    }
+
+    fn draw_rounded_rect_aa(&mut self, pos: Vec2, size: Vec2, radius: f32, color: Color) {
+        smooth::draw_rounded_rect_aa(self, pos, size, radius, color);
+    }
+
+    fn fill_rounded_rect_aa(&mut self, pos: Vec2, size: Vec2, radius: f32, color: Color) {
+        smooth::fill_rounded_rect_aa(self, pos, size, radius, color);
+    }
+
+    fn fill_polygon<P>(&mut self, points: &[Vec2], paint: P, rule: FillRule)
+    where
+        P: Paint,
+    {
+        polygon::fill_polygon(self, points, paint, rule);
+    }
+
+    fn fill_polygon_aa<P>(&mut self, points: &[Vec2], paint: P, rule: FillRule)
+    where
+        P: Paint,
+    {
+        polygon::fill_polygon_aa(self, points, paint, rule);
+    }
+
+    fn fill_contours<C, P>(&mut self, contours: &[C], paint: P, rule: FillRule)
+    where
+        C: AsRef<[Vec2]>,
+        P: Paint,
+    {
+        polygon::fill_contours(self, contours, paint, rule);
+    }
+
+    fn fill_contours_aa<C, P>(&mut self, contours: &[C], paint: P, rule: FillRule)
+    where
+        C: AsRef<[Vec2]>,
+        P: Paint,
+    {
+        polygon::fill_contours_aa(self, contours, paint, rule);
+    }
+
+    fn fill_path<P>(&mut self, path: &Path, paint: P, rule: FillRule)
+    where
+        P: Paint,
+    {
+        path::fill_path(self, path, paint, rule);
+    }
+
+    fn fill_path_aa<P>(&mut self, path: &Path, paint: P, rule: FillRule)
+    where
+        P: Paint,
+    {
+        path::fill_path_aa(self, path, paint, rule);
+    }
+
+    fn stroke_path<P>(&mut self, path: &Path, style: &StrokeStyle, paint: P)
+    where
+        P: Paint,
+    {
+        path::stroke_path(self, path, style, paint);
+    }
+
+    fn stroke_path_aa<P>(&mut self, path: &Path, style: &StrokeStyle, paint: P)
+    where
+        P: Paint,
+    {
+        path::stroke_path_aa(self, path, style, paint);
     }
 }
@@ -0,0 +1,248 @@
+use crate::core::*;
+
+// A rectangular region of another surface with its own coordinates. Views made
+// with `Surface::view_mut` are surfaces clipped to the region; the read-only
+// ones from `Surface::view` only give access to its pixels.
+pub struct SubSurface<T> {
+    surface: T,
+    rect: Rect,
+}
+
+impl<T> SubSurface<T> {
+    pub fn new(surface: T, rect: Rect) -> Self {
+        Self {
+            surface,
+            rect: rect.abs(),
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+}
+
+// Parent-space position of `pos` in `rect`, if it lies within `surface`.
+fn parent_pos<S>(surface: &S, rect: Rect, pos: IVec2) -> Option<IVec2>
+where
+    S: Surface + ?Sized,
+{
+    let pos = rect.pos + pos;
+    Rect::from_size(surface.size()).contains(pos).then_some(pos)
+}
+
+// Parent-space range of row `y` of `rect`, if it lies entirely within `bounds`.
+fn row_range(rect: Rect, bounds: Rect, y: i32) -> Option<(i32, std::ops::Range<usize>)> {
+    let row = Rect::new(rect.pos + ivec2(0, y), ivec2(rect.size.x, 1));
+    if row.intersect(bounds) != row {
+        return None;
+    }
+    let x = rect.pos.x as usize;
+    Some((row.pos.y, x..x + rect.size.x as usize))
+}
+
+impl<S> SubSurface<&S>
+where
+    S: Surface + ?Sized,
+{
+    pub fn get_pixel(&self, pos: IVec2) -> Color {
+        parent_pos(self.surface, self.rect, pos)
+            .map_or(Color::TRANSPARENT, |pos| self.surface.get_pixel(pos))
+    }
+
+    pub fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        parent_pos(self.surface, self.rect, pos).map_or(PremulColor::TRANSPARENT, |pos| {
+            self.surface.get_pixel_premul(pos)
+        })
+    }
+
+    pub fn size(&self) -> IVec2 {
+        self.rect.size
+    }
+
+    pub fn is_premultiplied(&self) -> bool {
+        self.surface.is_premultiplied()
+    }
+
+    pub fn row(&self, y: i32) -> Option<&[u32]> {
+        let (y, range) = row_range(self.rect, Rect::from_size(self.surface.size()), y)?;
+        Some(&self.surface.row(y)?[range])
+    }
+}
+
+impl<S> Surface for SubSurface<&mut S>
+where
+    S: Surface + ?Sized,
+{
+    fn get_pixel(&self, pos: IVec2) -> Color {
+        parent_pos(self.surface, self.rect, pos)
+            .map_or(Color::TRANSPARENT, |pos| self.surface.get_pixel(pos))
+    }
+
+    fn set_pixel(&mut self, pos: IVec2, color: Color) {
+        if self.clip_rect().contains(pos) {
+            self.surface.set_pixel(self.rect.pos + pos, color);
+        }
+    }
+
+    fn size(&self) -> IVec2 {
+        self.rect.size
+    }
+
+    fn clip_rect(&self) -> Rect {
+        self.surface
+            .clip_rect()
+            .intersect(self.rect)
+            .translate(-self.rect.pos)
+    }
+
+    fn is_premultiplied(&self) -> bool {
+        self.surface.is_premultiplied()
+    }
+
+    fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        parent_pos(self.surface, self.rect, pos).map_or(PremulColor::TRANSPARENT, |pos| {
+            self.surface.get_pixel_premul(pos)
+        })
+    }
+
+    fn set_pixel_premul(&mut self, pos: IVec2, color: PremulColor) {
+        if self.clip_rect().contains(pos) {
+            self.surface.set_pixel_premul(self.rect.pos + pos, color);
+        }
+    }
+
+    fn row(&self, y: i32) -> Option<&[u32]> {
+        let (y, range) = row_range(self.rect, Rect::from_size(self.surface.size()), y)?;
+        Some(&self.surface.row(y)?[range])
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.surface.push_clip(rect.abs().translate(self.rect.pos));
+    }
+
+    fn pop_clip(&mut self) {
+        self.surface.pop_clip();
+    }
+
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]> {
+        let (y, range) = row_range(self.rect, self.surface.clip_rect(), y)?;
+        Some(&mut self.surface.row_mut(y)?[range])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    // Every pixel holds its own position.
+    fn numbered(width: u32, height: u32) -> Image {
+        let mut image = Image::new(width, height, Color::TRANSPARENT);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                image.set_pixel(ivec2(x, y), Color::from_rgba(x as u8, y as u8, 0, 0xFF));
+            }
+        }
+        image
+    }
+
+    fn marked(image: &Image) -> Vec<IVec2> {
+        let size = image.size();
+        (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| ivec2(x, y)))
+            .filter(|&pos| image.get_pixel(pos) == Color::WHITE)
+            .collect()
+    }
+
+    #[test]
+    fn read_only_view() {
+        let image = numbered(8, 8);
+        let view = image.view(Rect::new(ivec2(2, 3), ivec2(4, 4)));
+        assert_eq!(view.size(), ivec2(4, 4));
+        assert_eq!(view.get_pixel(IVec2::ZERO), Color::from_rgba(2, 3, 0, 0xFF));
+        assert_eq!(view.get_pixel(ivec2(3, 3)), Color::from_rgba(5, 6, 0, 0xFF));
+        let row: Vec<_> = (2..6)
+            .map(|x| image.get_pixel(ivec2(x, 4)).as_u32())
+            .collect();
+        assert_eq!(view.row(1), Some(&row[..]));
+    }
+
+    #[test]
+    fn out_of_bounds_reads_are_transparent() {
+        let mut image = numbered(8, 8);
+        let rect = Rect::new(ivec2(-2, 6), ivec2(6, 6));
+        let view = image.view(rect);
+        assert_eq!(view.get_pixel(ivec2(2, 0)), Color::from_rgba(0, 6, 0, 0xFF));
+        assert_eq!(view.get_pixel(ivec2(1, 0)), Color::TRANSPARENT);
+        assert_eq!(view.get_pixel(ivec2(2, 2)), Color::TRANSPARENT);
+        assert_eq!(view.get_pixel_premul(ivec2(-9, 0)).as_u32(), 0);
+        // Rows reaching outside the parent aren't exposed.
+        assert_eq!(view.row(0), None);
+
+        let view = image.view_mut(rect);
+        assert_eq!(view.get_pixel(ivec2(2, 1)), Color::from_rgba(0, 7, 0, 0xFF));
+        assert_eq!(view.get_pixel(ivec2(1, 1)), Color::TRANSPARENT);
+        assert_eq!(view.get_pixel(ivec2(5, 5)), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn drawing_is_offset_and_clipped() {
+        let mut image = Image::new(8, 8, Color::BLACK);
+        let mut view = image.view_mut(Rect::new(ivec2(2, 3), ivec2(3, 2)));
+        assert_eq!(view.clip_rect(), Rect::new(IVec2::ZERO, ivec2(3, 2)));
+        view.set_pixel(IVec2::ZERO, Color::WHITE);
+        view.set_pixel(ivec2(3, 0), Color::WHITE);
+        view.set_pixel(ivec2(-1, 1), Color::WHITE);
+        assert_eq!(marked(&image), [ivec2(2, 3)]);
+
+        let mut view = image.view_mut(Rect::new(ivec2(2, 3), ivec2(3, 2)));
+        view.fill_rect(ivec2(-4, -4), ivec2(16, 16), Color::WHITE);
+        view.clear(Color::WHITE);
+        let expected: Vec<_> = (3..5)
+            .flat_map(|y| (2..5).map(move |x| ivec2(x, y)))
+            .collect();
+        assert_eq!(marked(&image), expected);
+    }
+
+    #[test]
+    fn clip_rect_is_translated() {
+        let mut image = Image::new(8, 8, Color::BLACK);
+        image.push_clip(Rect::new(IVec2::ZERO, ivec2(5, 5)));
+        let mut view = image.view_mut(Rect::new(ivec2(2, 3), ivec2(10, 10)));
+        assert_eq!(view.clip_rect(), Rect::new(IVec2::ZERO, ivec2(3, 2)));
+
+        // Clips pushed on the view are in its coordinates.
+        view.push_clip(Rect::new(ivec2(1, 1), ivec2(4, 4)));
+        assert_eq!(view.clip_rect(), Rect::new(ivec2(1, 1), ivec2(2, 1)));
+        view.clear(Color::WHITE);
+        view.pop_clip();
+        assert_eq!(view.clip_rect(), Rect::new(IVec2::ZERO, ivec2(3, 2)));
+        image.pop_clip();
+        assert_eq!(marked(&image), [ivec2(3, 4), ivec2(4, 4)]);
+
+        let view = image.view_mut(Rect::new(ivec2(6, 6), ivec2(4, 4)));
+        assert_eq!(view.clip_rect(), Rect::new(IVec2::ZERO, ivec2(2, 2)));
+        let view = image.view_mut(Rect::new(ivec2(9, 2), ivec2(4, 4)));
+        assert!(view.clip_rect().is_empty());
+    }
+
+    #[test]
+    fn nested_views() {
+        let mut image = numbered(16, 16);
+        let mut outer = image.view_mut(Rect::new(ivec2(2, 3), ivec2(8, 8)));
+        let mut inner = outer.view_mut(Rect::new(ivec2(4, 1), ivec2(6, 2)));
+        assert_eq!(
+            inner.get_pixel(IVec2::ZERO),
+            Color::from_rgba(6, 4, 0, 0xFF)
+        );
+        // The inner view is cut short by the outer one.
+        assert_eq!(inner.clip_rect(), Rect::new(IVec2::ZERO, ivec2(4, 2)));
+        assert_eq!(inner.view(Rect::from_size(ivec2(6, 2))).row(0), None);
+
+        inner.clear(Color::WHITE);
+        let expected: Vec<_> = (4..6)
+            .flat_map(|y| (6..10).map(move |x| ivec2(x, y)))
+            .collect();
+        assert_eq!(marked(&image), expected);
+    }
+}
@@ -0,0 +1,439 @@
+use std::f32::consts::SQRT_2;
+
+use crate::core::*;
+use crate::image::Image;
+use crate::text::{BitmapFont, draw_text};
+
+pub enum DrawCommand<'a> {
+    Clear(Color),
+    Blit {
+        image: &'a Image,
+        src: Rect,
+        dst: Rect,
+        options: BlitOptions,
+    },
+    BlitTransform {
+        image: &'a Image,
+        src: Rect,
+        transform: Affine2,
+        options: BlitOptions,
+    },
+    FillRect {
+        rect: Rect,
+        color: Color,
+    },
+    DrawRect {
+        rect: Rect,
+        color: Color,
+    },
+    Line {
+        a: IVec2,
+        b: IVec2,
+        color: Color,
+    },
+    FillCircle {
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    },
+    FillPath {
+        path: Path,
+        color: Color,
+        rule: FillRule,
+    },
+    StrokePath {
+        path: Path,
+        style: StrokeStyle,
+        color: Color,
+    },
+    Text {
+        font: &'a BitmapFont,
+        pos: IVec2,
+        color: Color,
+        text: String,
+    },
+}
+
+fn bounds_of(min: Vec2, max: Vec2) -> Rect {
+    Rect::from_min_max(min.floor().as_ivec2(), max.ceil().as_ivec2())
+}
+
+impl DrawCommand<'_> {
+    // The area the command may touch, or `None` if it is unbounded.
+    pub fn bounds(&self) -> Option<Rect> {
+        Some(match self {
+            Self::Clear(_) => return None,
+            Self::Blit { dst, .. } => dst.abs(),
+            Self::BlitTransform { src, transform, .. } => {
+                let size = src.size.abs().as_vec2();
+                let corners = [Vec2::ZERO, vec2(size.x, 0.0), vec2(0.0, size.y), size]
+                    .map(|corner| transform.transform_point2(corner));
+                let (min, max) = corners
+                    .iter()
+                    .fold((corners[0], corners[0]), |(min, max), p| {
+                        (min.min(*p), max.max(*p))
+                    });
+                bounds_of(min, max)
+            }
+            Self::FillRect { rect, .. } | Self::DrawRect { rect, .. } => rect.abs(),
+            Self::Line { a, b, .. } => {
+                // A rect can't be wider than `i32::MAX`, so lines longer than
+                // that lose their far negative end, which no surface has.
+                let max = a.max(*b).saturating_add(IVec2::ONE);
+                Rect::from_min_max(a.min(*b).max(max.saturating_sub(IVec2::MAX)), max)
+            }
+            Self::FillCircle { center, radius, .. } => {
+                bounds_of(center - radius - 1.0, center + radius + 1.0)
+            }
+            Self::FillPath { path, .. } => {
+                let (min, max) = path.bounds()?;
+                bounds_of(min - 1.0, max + 1.0)
+            }
+            Self::StrokePath { path, style, .. } => {
+                let (min, max) = path.bounds()?;
+                // Square caps reach half the width diagonally past the ends.
+                let extent = style.width / 2.0 * style.miter_limit.max(SQRT_2) + 1.0;
+                bounds_of(min - extent, max + extent)
+            }
+            Self::Text {
+                font, pos, text, ..
+            } => Rect::new(*pos, font.measure(text)),
+        })
+    }
+
+    pub fn draw<S>(&self, surface: &mut S)
+    where
+        S: Surface,
+    {
+        match self {
+            Self::Clear(color) => surface.clear(*color),
+            Self::Blit {
+                image,
+                src,
+                dst,
+                options,
+            } => surface.blit_with(
+                *image,
+                Some(src.pos),
+                Some(src.size),
+                Some(dst.pos),
+                Some(dst.size),
+                *options,
+            ),
+            Self::BlitTransform {
+                image,
+                src,
+                transform,
+                options,
+            } => {
+                surface.blit_transform(*image, Some(src.pos), Some(src.size), *transform, *options)
+            }
+            Self::FillRect { rect, color } => surface.fill_rect(rect.pos, rect.size, *color),
+            Self::DrawRect { rect, color } => surface.draw_rect(rect.pos, rect.size, *color),
+            Self::Line { a, b, color } => surface.draw_line(*a, *b, *color),
+            Self::FillCircle {
+                center,
+                radius,
+                color,
+            } => surface.fill_circle_aa(*center, *radius, *color),
+            Self::FillPath { path, color, rule } => surface.fill_path_aa(path, *color, *rule),
+            Self::StrokePath { path, style, color } => surface.stroke_path_aa(path, style, *color),
+            Self::Text {
+                font,
+                pos,
+                color,
+                text,
+            } => draw_text(surface, font, *pos, *color, text),
+        }
+    }
+}
+
+struct Item<'a> {
+    layer: i32,
+    z: f32,
+    bounds: Option<Rect>,
+    command: DrawCommand<'a>,
+}
+
+// Records draw commands to be sorted by layer, then by depth, and replayed to
+// any surface later.
+#[derive(Default)]
+pub struct DrawList<'a> {
+    items: Vec<Item<'a>>,
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn push(&mut self, layer: i32, z: f32, command: DrawCommand<'a>) -> &mut Self {
+        self.items.push(Item {
+            layer,
+            z,
+            bounds: command.bounds(),
+            command,
+        });
+        self
+    }
+
+    // Stable, so commands with equal keys keep their recording order.
+    pub fn sort(&mut self) {
+        self.items
+            .sort_by(|a, b| a.layer.cmp(&b.layer).then(a.z.total_cmp(&b.z)));
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &DrawCommand<'a>> {
+        self.items.iter().map(|item| &item.command)
+    }
+
+    // Draws every command in order, skipping the ones outside the surface's
+    // clip rectangle.
+    pub fn replay<S>(&self, surface: &mut S)
+    where
+        S: Surface,
+    {
+        for command in self.visible(surface.clip_rect()) {
+            command.draw(surface);
+        }
+    }
+
+    fn visible(&self, clip: Rect) -> impl Iterator<Item = &DrawCommand<'a>> {
+        self.items
+            .iter()
+            .filter(move |item| {
+                item.bounds
+                    .is_none_or(|bounds| !bounds.intersect(clip).is_empty())
+            })
+            .map(|item| &item.command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The smallest rectangle holding every pixel that differs from `color`.
+    fn touched(image: &Image, color: Color) -> Rect {
+        let (mut min, mut max) = (image.size(), IVec2::ZERO);
+        for y in 0..image.size().y {
+            for x in 0..image.size().x {
+                if image.get_pixel(ivec2(x, y)) != color {
+                    min = min.min(ivec2(x, y));
+                    max = max.max(ivec2(x + 1, y + 1));
+                }
+            }
+        }
+        Rect::from_min_max(min, max)
+    }
+
+    #[test]
+    fn stroke_bounds_cover_caps_and_joins() {
+        let mut path = Path::new();
+        path.move_to(vec2(30.0, 30.0))
+            .line_to(vec2(50.0, 50.0))
+            .line_to(vec2(70.0, 32.0));
+        for cap in [LineCap::Butt, LineCap::Round, LineCap::Square] {
+            for join in [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel] {
+                for miter_limit in [0.5, 1.0, 4.0, 10.0] {
+                    let style = StrokeStyle {
+                        width: 12.0,
+                        join,
+                        cap,
+                        miter_limit,
+                    };
+                    let command = DrawCommand::StrokePath {
+                        path: path.clone(),
+                        style,
+                        color: Color::WHITE,
+                    };
+                    let mut image = Image::new(100, 100, Color::BLACK);
+                    command.draw(&mut image);
+                    let touched = touched(&image, Color::BLACK);
+                    let bounds = command.bounds().unwrap();
+                    assert_eq!(touched.intersect(bounds), touched, "{style:?}");
+                }
+            }
+        }
+    }
+
+    fn fill(x: i32, y: i32, color: u32) -> DrawCommand<'static> {
+        DrawCommand::FillRect {
+            rect: Rect::new(ivec2(x, y), ivec2(2, 2)),
+            color: Color::from_u32(color),
+        }
+    }
+
+    // The color of every rect fill, and 0 for other commands.
+    fn ids<'b>(commands: impl Iterator<Item = &'b DrawCommand<'b>>) -> Vec<u32> {
+        commands
+            .map(|command| match command {
+                DrawCommand::FillRect { color, .. } => color.as_u32(),
+                _ => 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sort_is_stable_across_layer_and_z() {
+        let mut list = DrawList::new();
+        list.push(1, 0.0, fill(0, 0, 1))
+            .push(0, 2.0, fill(0, 0, 2))
+            .push(1, -1.0, fill(0, 0, 3))
+            .push(0, 2.0, fill(0, 0, 4))
+            .push(-1, 9.0, fill(0, 0, 5))
+            .push(1, 0.0, fill(0, 0, 6))
+            .push(0, 0.5, fill(0, 0, 7))
+            .push(1, 0.0, fill(0, 0, 8));
+        assert_eq!(ids(list.commands()), [1, 2, 3, 4, 5, 6, 7, 8]);
+        list.sort();
+        assert_eq!(ids(list.commands()), [5, 7, 2, 4, 3, 1, 6, 8]);
+        list.sort();
+        assert_eq!(ids(list.commands()), [5, 7, 2, 4, 3, 1, 6, 8]);
+        assert_eq!(list.len(), 8);
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn replay_culls_outside_the_clip() {
+        let mut list = DrawList::new();
+        list.push(0, 0.0, DrawCommand::Clear(Color::BLACK))
+            .push(0, 0.0, fill(1, 1, 0xFFFF0000))
+            .push(0, 0.0, fill(3, 3, 0xFF00FF00))
+            .push(0, 0.0, fill(4, 0, 0xFF0000FF))
+            .push(0, 0.0, fill(-1, 1, 0xFFFFFF00))
+            .push(0, 0.0, fill(-2, 5, 0xFFFF00FF))
+            .push(0, 0.0, fill(20, 20, 0xFF00FFFF))
+            .push(
+                0,
+                0.0,
+                DrawCommand::FillPath {
+                    path: Path::new(),
+                    color: Color::WHITE,
+                    rule: FillRule::NonZero,
+                },
+            );
+        let clip = Rect::new(IVec2::ZERO, ivec2(4, 4));
+        // Clears and empty paths have no bounds and are never culled.
+        assert_eq!(
+            ids(list.visible(clip)),
+            [0, 0xFFFF0000, 0xFF00FF00, 0xFFFFFF00, 0]
+        );
+
+        let mut image = Image::new(8, 8, Color::WHITE);
+        image.with_clip(clip, |image| list.replay(image));
+        assert_eq!(image.get_pixel(ivec2(0, 0)), Color::BLACK);
+        assert_eq!(image.get_pixel(ivec2(1, 1)), Color::RED);
+        assert_eq!(image.get_pixel(ivec2(3, 3)), Color::GREEN);
+        assert_eq!(image.get_pixel(ivec2(4, 4)), Color::WHITE);
+        assert_eq!(image.get_pixel(ivec2(4, 0)), Color::WHITE);
+        assert_eq!(image.get_pixel(ivec2(0, 1)), Color::from_u32(0xFFFFFF00));
+
+        let mut image = Image::new(8, 8, Color::WHITE);
+        list.replay(&mut image);
+        assert_eq!(image.get_pixel(ivec2(4, 0)), Color::BLUE);
+        assert_eq!(image.get_pixel(ivec2(4, 4)), Color::GREEN);
+        assert_eq!(image.get_pixel(ivec2(6, 6)), Color::BLACK);
+    }
+
+    #[test]
+    fn line_bounds() {
+        let line = |a, b| DrawCommand::Line {
+            a,
+            b,
+            color: Color::WHITE,
+        };
+        let bounds = |a, b| line(a, b).bounds().unwrap();
+        assert_eq!(
+            bounds(ivec2(3, 7), ivec2(1, 2)),
+            Rect::from_min_max(ivec2(1, 2), ivec2(4, 8))
+        );
+        assert_eq!(
+            bounds(ivec2(5, 5), ivec2(5, 5)),
+            Rect::new(ivec2(5, 5), IVec2::ONE)
+        );
+        assert_eq!(
+            bounds(IVec2::MAX, IVec2::ZERO),
+            Rect::new(IVec2::ZERO, IVec2::MAX)
+        );
+        assert_eq!(
+            bounds(ivec2(i32::MIN, 3), ivec2(i32::MAX, 3)),
+            Rect::new(ivec2(0, 3), ivec2(i32::MAX, 1))
+        );
+        assert_eq!(
+            bounds(IVec2::MIN, ivec2(-1, -1)),
+            Rect::new(IVec2::MIN + 1, IVec2::MAX)
+        );
+
+        let mut image = Image::new(16, 16, Color::BLACK);
+        let command = line(ivec2(14, 2), ivec2(3, 11));
+        command.draw(&mut image);
+        assert_eq!(touched(&image, Color::BLACK), command.bounds().unwrap());
+    }
+
+    #[test]
+    fn blit_and_text_bounds() {
+        let mut sprite = Image::new(6, 4, Color::RED);
+        sprite.set_pixel(ivec2(5, 3), Color::BLUE);
+        let font = BitmapFont::default();
+        let commands = [
+            DrawCommand::Blit {
+                image: &sprite,
+                src: Rect::from_size(sprite.size()),
+                dst: Rect::new(ivec2(10, 12), ivec2(6, 4)),
+                options: BlitOptions::default(),
+            },
+            DrawCommand::Blit {
+                image: &sprite,
+                src: Rect::from_size(sprite.size()),
+                dst: Rect::new(ivec2(30, 20), ivec2(-12, -8)),
+                options: BlitOptions::default(),
+            },
+            DrawCommand::BlitTransform {
+                image: &sprite,
+                src: Rect::from_size(sprite.size()),
+                transform: Affine2::from_scale_angle_translation(
+                    vec2(3.0, 2.0),
+                    0.6,
+                    vec2(30.0, 10.0),
+                ),
+                options: BlitOptions::default(),
+            },
+            DrawCommand::Text {
+                font: &font,
+                pos: ivec2(4, 30),
+                color: Color::WHITE,
+                text: "Hello\n\tWorld!".to_string(),
+            },
+        ];
+        for (index, command) in commands.iter().enumerate() {
+            let mut image = Image::new(64, 64, Color::BLACK);
+            command.draw(&mut image);
+            let touched = touched(&image, Color::BLACK);
+            let bounds = command.bounds().unwrap();
+            assert!(!touched.is_empty(), "{index}");
+            assert_eq!(touched.intersect(bounds), touched, "{index}: {bounds:?}");
+        }
+        assert_eq!(
+            commands[1].bounds(),
+            Some(Rect::new(ivec2(18, 12), ivec2(12, 8)))
+        );
+        assert_eq!(
+            commands[3].bounds(),
+            Some(Rect::new(ivec2(4, 30), font.measure("Hello\n\tWorld!")))
+        );
+    }
+}
@@ -1,8 +1,11 @@
+use crate::core::simd;
 use crate::core::*;
 
 pub struct Image {
     pixels: Box<[u32]>,
     size: IVec2,
+    premultiplied: bool,
+    clip: ClipStack,
 }
 
 impl Image {
@@ -11,6 +14,8 @@ impl Image {
         Self {
             pixels: vec![color.as_u32(); (width * height) as usize].into_boxed_slice(),
             size: ivec2(width as i32, height as i32),
+            premultiplied: false,
+            clip: ClipStack::new(),
         }
     }
 
@@ -28,22 +33,291 @@ impl Image {
         }
         result
     }
+
+    pub fn resize(&self, width: u32, height: u32, filter: Filter) -> Self {
+        let mut result = Self::new(width, height, Color::TRANSPARENT);
+        if self.premultiplied {
+            result.premultiply();
+        }
+        let options = BlitOptions {
+            blend: BlendMode::Replace,
+            filter,
+            ..Default::default()
+        };
+        result.blit_with(self, None, None, None, Some(result.size), options);
+        result
+    }
+
+    pub fn premultiply(&mut self) {
+        if self.premultiplied {
+            return;
+        }
+        for pixel in self.pixels.iter_mut() {
+            *pixel = PremulColor::from_color(Color::from_u32(*pixel)).as_u32();
+        }
+        self.premultiplied = true;
+    }
+
+    pub fn unpremultiply(&mut self) {
+        if !self.premultiplied {
+            return;
+        }
+        for pixel in self.pixels.iter_mut() {
+            *pixel = PremulColor::from_u32(*pixel).to_color().as_u32();
+        }
+        self.premultiplied = false;
+    }
 }
 
 impl Surface for Image {
     fn get_pixel(&self, pos: IVec2) -> Color {
-        Color::from_u32(self.pixels[Self::index(pos, self.size.x) as usize])
+        let pixel = self.pixels[Self::index(pos, self.size.x) as usize];
+        if self.premultiplied {
+            PremulColor::from_u32(pixel).to_color()
+        } else {
+            Color::from_u32(pixel)
+        }
     }
 
     fn set_pixel(&mut self, pos: IVec2, color: Color) {
-        self.pixels[Self::index(pos, self.size.x) as usize] = color.as_u32();
+        self.pixels[Self::index(pos, self.size.x) as usize] = if self.premultiplied {
+            PremulColor::from_color(color).as_u32()
+        } else {
+            color.as_u32()
+        };
     }
 
     fn size(&self) -> IVec2 {
         self.size
     }
 
+    fn clip_rect(&self) -> Rect {
+        self.clip.clip(Rect::from_size(self.size))
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.clip.push(rect, Rect::from_size(self.size));
+    }
+
+    fn pop_clip(&mut self) {
+        self.clip.pop();
+    }
+
+    fn row(&self, y: i32) -> Option<&[u32]> {
+        let start = usize::try_from(y).ok()? * self.size.x as usize;
+        self.pixels.get(start..start + self.size.x as usize)
+    }
+
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]> {
+        let start = usize::try_from(y).ok()? * self.size.x as usize;
+        self.pixels.get_mut(start..start + self.size.x as usize)
+    }
+
+    fn pixels(&self) -> Option<&[u32]> {
+        Some(&self.pixels)
+    }
+
+    fn pixels_mut(&mut self) -> Option<&mut [u32]> {
+        Some(&mut self.pixels)
+    }
+
     fn clear(&mut self, color: Color) {
-        self.pixels.fill(color.as_u32());
+        let raw = if self.premultiplied {
+            PremulColor::from_color(color).as_u32()
+        } else {
+            color.as_u32()
+        };
+        if self.clip.is_empty() {
+            simd::fill(&mut self.pixels, raw);
+            return;
+        }
+        let clip = self.clip_rect();
+        if clip.is_empty() {
+            return;
+        }
+        for y in clip.min().y..clip.max().y {
+            let start = Self::index(ivec2(clip.pos.x, y), self.size.x) as usize;
+            simd::fill(&mut self.pixels[start..start + clip.size.x as usize], raw);
+        }
+    }
+
+    fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        let pixel = self.pixels[Self::index(pos, self.size.x) as usize];
+        if self.premultiplied {
+            PremulColor::from_u32(pixel)
+        } else {
+            PremulColor::from_color(Color::from_u32(pixel))
+        }
+    }
+
+    fn set_pixel_premul(&mut self, pos: IVec2, color: PremulColor) {
+        self.pixels[Self::index(pos, self.size.x) as usize] = if self.premultiplied {
+            color.as_u32()
+        } else {
+            color.to_color().as_u32()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let mut image = Image::new(3, 2, Color::BLACK);
+        image.set_pixel(ivec2(1, 1), Color::WHITE);
+        assert_eq!(image.stride(), Some(3));
+        assert_eq!(image.row(0), Some(&[Color::BLACK.as_u32(); 3][..]));
+        assert_eq!(image.row(1).unwrap()[1], Color::WHITE.as_u32());
+        assert_eq!(image.row(-1), None);
+        assert_eq!(image.row(2), None);
+        assert_eq!(image.row(i32::MAX), None);
+        assert!(image.row_mut(i32::MIN).is_none());
+        assert!(image.row_mut(2).is_none());
+        image.row_mut(0).unwrap().fill(Color::RED.as_u32());
+        assert_eq!(image.get_pixel(ivec2(2, 0)), Color::RED);
+    }
+
+    // Positions of the pixels that aren't black.
+    fn painted(image: &Image) -> Vec<IVec2> {
+        let size = image.size();
+        (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| ivec2(x, y)))
+            .filter(|&pos| image.get_pixel(pos) != Color::BLACK)
+            .collect()
+    }
+
+    fn rect_positions(rect: Rect) -> Vec<IVec2> {
+        (rect.min().y..rect.max().y)
+            .flat_map(|y| (rect.min().x..rect.max().x).map(move |x| ivec2(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn clear_respects_clip() {
+        for premultiplied in [false, true] {
+            let mut image = Image::new(8, 8, Color::BLACK);
+            if premultiplied {
+                image.premultiply();
+            }
+            let clip = Rect::new(ivec2(2, 3), ivec2(3, 2));
+            let size = image.with_clip(clip, |image| {
+                image.clear(Color::WHITE);
+                image.clip_rect().size
+            });
+            assert_eq!(size, clip.size);
+            assert_eq!(image.clip_rect(), Rect::from_size(image.size()));
+            assert_eq!(painted(&image), rect_positions(clip));
+            assert_eq!(image.get_pixel(ivec2(2, 3)), Color::WHITE);
+        }
+    }
+
+    #[test]
+    fn empty_clips_draw_nothing() {
+        for clip in [
+            Rect::new(ivec2(20, 7), ivec2(4, 4)),
+            Rect::new(ivec2(2, 20), ivec2(4, 4)),
+            Rect::new(ivec2(-9, -9), ivec2(4, 4)),
+            Rect::new(ivec2(2, 2), IVec2::ZERO),
+        ] {
+            let mut image = Image::new(8, 8, Color::BLACK);
+            image.with_clip(clip, |image| {
+                assert!(image.clip_rect().is_empty());
+                image.clear(Color::WHITE);
+                image.fill_rect(IVec2::ZERO, ivec2(8, 8), Color::WHITE);
+                image.blit(&Image::new(8, 8, Color::RED), None, None, None, None);
+            });
+            assert!(painted(&image).is_empty(), "{clip:?}");
+        }
+    }
+
+    #[test]
+    fn blits_respect_clip() {
+        let src = Image::new(4, 4, Color::RED);
+        let clip = Rect::new(ivec2(2, 1), ivec2(4, 2));
+        for dst_size in [None, Some(ivec2(8, 8))] {
+            let mut image = Image::new(8, 8, Color::BLACK);
+            image.with_clip(clip, |image| {
+                image.blit(&src, None, None, Some(IVec2::ZERO), dst_size);
+            });
+            let expected = match dst_size {
+                None => Rect::new(ivec2(2, 1), ivec2(2, 2)),
+                Some(_) => clip,
+            };
+            assert_eq!(painted(&image), rect_positions(expected), "{dst_size:?}");
+        }
+    }
+
+    fn pattern(premultiplied: bool) -> Image {
+        let mut image = Image::new(16, 16, Color::TRANSPARENT);
+        for y in 0..16 {
+            for x in 0..16 {
+                let (x8, y8) = (x as u8, y as u8);
+                let color = Color::from_rgba(x8 * 16, y8 * 16, 0xFF - x8 * 8, (x8 * 17) ^ y8);
+                image.set_pixel(ivec2(x, y), color);
+            }
+        }
+        if premultiplied {
+            image.premultiply();
+        }
+        image
+    }
+
+    #[test]
+    fn premultiply_round_trip_at_alpha_extremes() {
+        let mut image = Image::new(3, 1, Color::TRANSPARENT);
+        image.set_pixel(ivec2(0, 0), Color::from_u32(0xFF123456));
+        image.set_pixel(ivec2(1, 0), Color::from_u32(0x00FFFFFF));
+        image.set_pixel(ivec2(2, 0), Color::from_u32(0x80FF8000));
+        image.premultiply();
+        assert!(image.is_premultiplied());
+        assert_eq!(
+            image.pixels(),
+            Some(&[0xFF123456, 0x00000000, 0x80804000][..])
+        );
+        assert_eq!(image.get_pixel(ivec2(0, 0)), Color::from_u32(0xFF123456));
+        image.premultiply();
+        assert_eq!(image.pixels().unwrap()[2], 0x80804000);
+        image.unpremultiply();
+        assert!(!image.is_premultiplied());
+        // Color under full transparency is lost.
+        assert_eq!(
+            image.pixels(),
+            Some(&[0xFF123456, 0x00000000, 0x80FF8000][..])
+        );
+    }
+
+    #[test]
+    fn premultiplied_blits_match_straight() {
+        for blend in [
+            BlendMode::SourceOver,
+            BlendMode::Multiply,
+            BlendMode::Additive,
+        ] {
+            let options = BlitOptions {
+                blend,
+                ..Default::default()
+            };
+            let mut expected = Image::new(16, 16, Color::from_u32(0xFF406080));
+            expected.blit_with(&pattern(false), None, None, None, None, options);
+            for (src_premul, dst_premul) in [(true, false), (false, true), (true, true)] {
+                let mut dst = Image::new(16, 16, Color::from_u32(0xFF406080));
+                if dst_premul {
+                    dst.premultiply();
+                }
+                dst.blit_with(&pattern(src_premul), None, None, None, None, options);
+                dst.unpremultiply();
+                assert_eq!(
+                    dst.pixels(),
+                    expected.pixels(),
+                    "{blend:?} {src_premul} {dst_premul}"
+                );
+            }
+        }
     }
 }
@@ -1,5 +1,14 @@
 pub mod core;
+pub mod draw_list;
 pub mod image;
+pub mod mask;
+pub mod text;
 
 #[cfg(feature = "window")]
 pub mod window;
+
+#[cfg(feature = "svg")]
+pub mod svg;
+
+#[cfg(feature = "parallel")]
+pub mod parallel;
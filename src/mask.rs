@@ -0,0 +1,389 @@
+use crate::core::*;
+
+// Coverage from 0 to 255 per pixel. As a surface it reads back as white with
+// the coverage as alpha and stores the alpha of whatever is drawn into it, so
+// shapes, text and blits all work as ways to build a mask.
+pub struct Mask {
+    data: Box<[u8]>,
+    size: IVec2,
+    clip: ClipStack,
+}
+
+impl Mask {
+    pub fn new(width: u32, height: u32, coverage: u8) -> Self {
+        debug_assert!(width <= i32::MAX as u32 && height <= i32::MAX as u32);
+        Self {
+            data: vec![coverage; (width * height) as usize].into_boxed_slice(),
+            size: ivec2(width as i32, height as i32),
+            clip: ClipStack::new(),
+        }
+    }
+
+    // A mask of the same size holding the alpha channel of `surface`.
+    pub fn from_alpha<S>(surface: &S) -> Self
+    where
+        S: Surface,
+    {
+        let size = surface.size();
+        let mut result = Self::new(size.x as u32, size.y as u32, 0);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let pos = ivec2(x, y);
+                result.set_coverage(pos, surface.get_pixel(pos).a());
+            }
+        }
+        result
+    }
+
+    pub fn coverage(&self, pos: IVec2) -> u8 {
+        self.data[Self::index(pos, self.size.x) as usize]
+    }
+
+    pub fn set_coverage(&mut self, pos: IVec2, coverage: u8) {
+        self.data[Self::index(pos, self.size.x) as usize] = coverage;
+    }
+
+    pub fn invert(&mut self) {
+        for coverage in self.data.iter_mut() {
+            *coverage = !*coverage;
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl Surface for Mask {
+    fn get_pixel(&self, pos: IVec2) -> Color {
+        Color::WHITE.with_a(self.coverage(pos))
+    }
+
+    fn set_pixel(&mut self, pos: IVec2, color: Color) {
+        self.set_coverage(pos, color.a());
+    }
+
+    fn size(&self) -> IVec2 {
+        self.size
+    }
+
+    fn clip_rect(&self) -> Rect {
+        self.clip.clip(Rect::from_size(self.size))
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.clip.push(rect, Rect::from_size(self.size));
+    }
+
+    fn pop_clip(&mut self) {
+        self.clip.pop();
+    }
+
+    fn clear(&mut self, color: Color) {
+        if self.clip.is_empty() {
+            self.data.fill(color.a());
+            return;
+        }
+        let clip = self.clip_rect();
+        if clip.is_empty() {
+            return;
+        }
+        for y in clip.min().y..clip.max().y {
+            let start = Self::index(ivec2(clip.pos.x, y), self.size.x) as usize;
+            self.data[start..start + clip.size.x as usize].fill(color.a());
+        }
+    }
+}
+
+// A surface that blends everything drawn into it with the pixels underneath
+// by the coverage of `mask`, placed at `pos`. Drawing outside the mask is
+// clipped away.
+pub struct Masked<'a, S: ?Sized> {
+    surface: &'a mut S,
+    mask: &'a Mask,
+    pos: IVec2,
+}
+
+impl<'a, S> Masked<'a, S>
+where
+    S: Surface + ?Sized,
+{
+    pub fn new(surface: &'a mut S, mask: &'a Mask, pos: IVec2) -> Self {
+        Self { surface, mask, pos }
+    }
+
+    fn coverage(&self, pos: IVec2) -> u8 {
+        let pos = pos - self.pos;
+        if Rect::from_size(self.mask.size()).contains(pos) {
+            self.mask.coverage(pos)
+        } else {
+            0x00
+        }
+    }
+
+    // Runs `draw` on the underlying pixel and keeps the part of the change
+    // the mask lets through.
+    fn apply<F>(&mut self, pos: IVec2, draw: F)
+    where
+        F: FnOnce(&mut S),
+    {
+        match self.coverage(pos) {
+            0x00 => {}
+            0xFF => draw(self.surface),
+            coverage => {
+                let before = self.surface.get_pixel_premul(pos);
+                draw(self.surface);
+                let after = self.surface.get_pixel_premul(pos);
+                self.surface
+                    .set_pixel_premul(pos, before.lerp(after, coverage));
+            }
+        }
+    }
+}
+
+impl<S> Surface for Masked<'_, S>
+where
+    S: Surface + ?Sized,
+{
+    fn get_pixel(&self, pos: IVec2) -> Color {
+        self.surface.get_pixel(pos)
+    }
+
+    fn set_pixel(&mut self, pos: IVec2, color: Color) {
+        self.apply(pos, |surface| surface.set_pixel(pos, color));
+    }
+
+    fn size(&self) -> IVec2 {
+        self.surface.size()
+    }
+
+    fn clip_rect(&self) -> Rect {
+        let mask = Rect::new(self.pos, self.mask.size());
+        self.surface.clip_rect().intersect(mask)
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.surface.push_clip(rect);
+    }
+
+    fn pop_clip(&mut self) {
+        self.surface.pop_clip();
+    }
+
+    fn is_premultiplied(&self) -> bool {
+        self.surface.is_premultiplied()
+    }
+
+    fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        self.surface.get_pixel_premul(pos)
+    }
+
+    fn set_pixel_premul(&mut self, pos: IVec2, color: PremulColor) {
+        self.apply(pos, |surface| surface.set_pixel_premul(pos, color));
+    }
+
+    fn blend_pixel(&mut self, pos: IVec2, color: Color, mode: BlendMode) {
+        self.apply(pos, |surface| surface.blend_pixel(pos, color, mode));
+    }
+
+    fn blend_pixel_premul(&mut self, pos: IVec2, color: PremulColor, mode: BlendMode) {
+        self.apply(pos, |surface| surface.blend_pixel_premul(pos, color, mode));
+    }
+}
+
+// Draws `src` at `pos` through `mask` placed at `mask_pos`.
+pub fn blit_masked<D, S>(
+    dst: &mut D,
+    src: &S,
+    pos: IVec2,
+    mask: &Mask,
+    mask_pos: IVec2,
+    options: BlitOptions,
+) where
+    D: Surface,
+    S: Surface,
+{
+    let mut masked = Masked::new(dst, mask, mask_pos);
+    masked.blit_with(src, None, None, Some(pos), None, options);
+}
+
+// Fills the area covered by `mask` placed at `pos` with `paint`.
+pub fn fill_masked<D, P>(dst: &mut D, mask: &Mask, pos: IVec2, paint: P)
+where
+    D: Surface,
+    P: Paint,
+{
+    let mut masked = Masked::new(dst, mask, pos);
+    masked.fill_rect(pos, mask.size(), paint);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    const MODES: [BlendMode; 4] = [
+        BlendMode::SourceOver,
+        BlendMode::Replace,
+        BlendMode::Multiply,
+        BlendMode::Additive,
+    ];
+
+    fn mask(width: u32, coverage: &[u8]) -> Mask {
+        let mut mask = Mask::new(width, coverage.len() as u32 / width, 0);
+        mask.data_mut().copy_from_slice(coverage);
+        mask
+    }
+
+    fn pixels(image: &Image) -> Vec<u32> {
+        let size = image.size();
+        (0..size.x * size.y)
+            .map(|i| image.get_pixel(ivec2(i % size.x, i / size.x)).as_u32())
+            .collect()
+    }
+
+    #[test]
+    fn apply_by_coverage() {
+        let mask = mask(3, &[0x00, 0xFF, 0x80]);
+        let mut image = Image::new(3, 1, Color::BLACK);
+        let mut masked = Masked::new(&mut image, &mask, IVec2::ZERO);
+        for x in 0..3 {
+            masked.set_pixel(ivec2(x, 0), Color::WHITE);
+        }
+        assert_eq!(pixels(&image), [0xFF000000, 0xFFFFFFFF, 0xFF808080]);
+
+        let background = Color::from_u32(0xFF4080C0);
+        let source = Color::from_u32(0x80FF4020);
+        for mode in MODES {
+            let mut unmasked = Image::new(1, 1, background);
+            unmasked.blend_pixel(IVec2::ZERO, source, mode);
+            let full = unmasked.get_pixel_premul(IVec2::ZERO);
+
+            let mut image = Image::new(3, 1, background);
+            let mut masked = Masked::new(&mut image, &mask, IVec2::ZERO);
+            for x in 0..3 {
+                masked.blend_pixel(ivec2(x, 0), source, mode);
+            }
+            let before = PremulColor::from_color(background);
+            let expected = [before, full, before.lerp(full, 0x80)].map(|c| c.to_color().as_u32());
+            assert_eq!(pixels(&image), expected, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn clip_rect_is_mask_placement() {
+        let mask = Mask::new(6, 6, 0xFF);
+        let mut image = Image::new(16, 16, Color::BLACK);
+        let masked = Masked::new(&mut image, &mask, ivec2(4, 6));
+        assert_eq!(masked.clip_rect(), Rect::new(ivec2(4, 6), ivec2(6, 6)));
+        let masked = Masked::new(&mut image, &mask, ivec2(-2, 13));
+        assert_eq!(masked.clip_rect(), Rect::new(ivec2(0, 13), ivec2(4, 3)));
+
+        image.push_clip(Rect::new(ivec2(2, 2), ivec2(6, 6)));
+        let mut masked = Masked::new(&mut image, &mask, ivec2(4, 6));
+        assert_eq!(masked.clip_rect(), Rect::new(ivec2(4, 6), ivec2(4, 2)));
+        masked.fill_rect(IVec2::ZERO, ivec2(16, 16), Color::WHITE);
+        image.pop_clip();
+        for y in 0..16 {
+            for x in 0..16 {
+                let inside = (4..8).contains(&x) && (6..8).contains(&y);
+                assert_eq!(image.get_pixel(ivec2(x, y)) == Color::WHITE, inside);
+            }
+        }
+    }
+
+    #[test]
+    fn clear_respects_clip() {
+        let mut mask = Mask::new(4, 4, 0);
+        mask.with_clip(Rect::new(ivec2(1, 1), ivec2(2, 2)), |mask| {
+            mask.clear(Color::WHITE)
+        });
+        #[rustfmt::skip]
+        let expected = [
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0xFF, 0xFF, 0x00,
+            0x00, 0xFF, 0xFF, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(mask.data(), expected);
+        mask.with_clip(Rect::new(ivec2(9, 3), ivec2(2, 2)), |mask| {
+            mask.clear(Color::TRANSPARENT)
+        });
+        assert_eq!(mask.data(), expected);
+        mask.clear(Color::WHITE.with_a(7));
+        assert!(mask.data().iter().all(|&c| c == 7));
+    }
+
+    #[test]
+    fn from_alpha_and_invert() {
+        let mut image = Image::new(4, 1, Color::TRANSPARENT);
+        for x in 0..4 {
+            image.set_pixel(ivec2(x, 0), Color::RED.with_a(x as u8 * 85));
+        }
+        let mut mask = Mask::from_alpha(&image);
+        assert_eq!(mask.size(), ivec2(4, 1));
+        assert_eq!(mask.data(), [0, 85, 170, 255]);
+        assert_eq!(mask.get_pixel(ivec2(1, 0)), Color::WHITE.with_a(85));
+        mask.invert();
+        assert_eq!(mask.data(), [255, 170, 85, 0]);
+
+        // Drawing into a mask stores the alpha of what was drawn.
+        mask.set_pixel(ivec2(1, 0), Color::BLUE.with_a(9));
+        mask.fill_rect(ivec2(2, 0), ivec2(2, 1), Color::BLUE);
+        assert_eq!(mask.data(), [255, 9, 255, 255]);
+    }
+
+    #[test]
+    fn blit_through_mask() {
+        let src = Image::new(4, 4, Color::RED);
+        let mask = mask(4, &[0xFF, 0xFF, 0x00, 0x00].repeat(4));
+        for (mask_pos, columns) in [
+            (ivec2(2, 2), 2..4),
+            (ivec2(4, 2), 4..6),
+            (ivec2(0, 2), 2..2),
+        ] {
+            let mut image = Image::new(8, 8, Color::BLACK);
+            blit_masked(
+                &mut image,
+                &src,
+                ivec2(2, 2),
+                &mask,
+                mask_pos,
+                BlitOptions::default(),
+            );
+            for y in 0..8 {
+                for x in 0..8 {
+                    let red = columns.contains(&x) && (2..6).contains(&y);
+                    let expected = if red { Color::RED } else { Color::BLACK };
+                    assert_eq!(image.get_pixel(ivec2(x, y)), expected, "{mask_pos} {x} {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fill_with_gradient() {
+        let gradient = Gradient::linear(Vec2::ZERO, vec2(16.0, 0.0))
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::BLUE);
+        let mask = mask(8, &[0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0x80, 0xFF, 0xFF]);
+        let mut image = Image::new(16, 2, Color::BLACK);
+        fill_masked(&mut image, &mask, ivec2(4, 1), &gradient);
+        for x in 0..16 {
+            let pos = ivec2(x, 1);
+            let expected = match x - 4 {
+                0..=2 | 4 | 6 | 7 => gradient.color_at(pos),
+                5 => PremulColor::from_color(Color::BLACK)
+                    .lerp(gradient.color_at(pos).into(), 0x80)
+                    .to_color(),
+                _ => Color::BLACK,
+            };
+            assert_eq!(image.get_pixel(pos), expected, "{x}");
+            assert_eq!(image.get_pixel(ivec2(x, 0)), Color::BLACK);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::thread;
+
+use crate::core::*;
+
+// A horizontal band of a surface. It keeps the coordinates and size of the
+// whole surface but clips all drawing to its own rows, so every band can run
+// the same draw calls and produce exactly the pixels a single-threaded render
+// would.
+pub struct Band<'a> {
+    target: Target<'a>,
+    size: IVec2,
+    first_row: i32,
+    clip: Rect,
+    stack: ClipStack,
+    premultiplied: bool,
+}
+
+enum Target<'a> {
+    // The band's own rows of a surface that exposes its pixels.
+    Rows(&'a mut [u32]),
+    // A whole surface that doesn't, drawn on a single thread.
+    Surface(&'a mut dyn PixelAccess),
+}
+
+// The per-pixel part of `Surface`, usable as a trait object.
+trait PixelAccess {
+    fn get(&self, pos: IVec2) -> Color;
+    fn set(&mut self, pos: IVec2, color: Color);
+    fn get_premul(&self, pos: IVec2) -> PremulColor;
+    fn set_premul(&mut self, pos: IVec2, color: PremulColor);
+    fn row(&self, y: i32) -> Option<&[u32]>;
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]>;
+}
+
+impl<S> PixelAccess for S
+where
+    S: Surface,
+{
+    fn get(&self, pos: IVec2) -> Color {
+        self.get_pixel(pos)
+    }
+
+    fn set(&mut self, pos: IVec2, color: Color) {
+        self.set_pixel(pos, color);
+    }
+
+    fn get_premul(&self, pos: IVec2) -> PremulColor {
+        self.get_pixel_premul(pos)
+    }
+
+    fn set_premul(&mut self, pos: IVec2, color: PremulColor) {
+        self.set_pixel_premul(pos, color);
+    }
+
+    fn row(&self, y: i32) -> Option<&[u32]> {
+        Surface::row(self, y)
+    }
+
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]> {
+        Surface::row_mut(self, y)
+    }
+}
+
+impl Band<'_> {
+    fn index(&self, pos: IVec2) -> usize {
+        ((pos.y - self.first_row) * self.size.x + pos.x) as usize
+    }
+
+    fn row_range(&self, y: i32) -> Option<std::ops::Range<usize>> {
+        if y < self.clip.min().y || y >= self.clip.max().y {
+            return None;
+        }
+        let start = self.index(ivec2(0, y));
+        Some(start..start + self.size.x as usize)
+    }
+}
+
+impl Surface for Band<'_> {
+    fn get_pixel(&self, pos: IVec2) -> Color {
+        let pixels = match &self.target {
+            Target::Rows(pixels) => pixels,
+            Target::Surface(surface) => return surface.get(pos),
+        };
+        let pixel = pixels[self.index(pos)];
+        if self.premultiplied {
+            PremulColor::from_u32(pixel).to_color()
+        } else {
+            Color::from_u32(pixel)
+        }
+    }
+
+    fn set_pixel(&mut self, pos: IVec2, color: Color) {
+        let index = self.index(pos);
+        let pixels = match &mut self.target {
+            Target::Rows(pixels) => pixels,
+            Target::Surface(surface) => return surface.set(pos, color),
+        };
+        pixels[index] = if self.premultiplied {
+            PremulColor::from_color(color).as_u32()
+        } else {
+            color.as_u32()
+        };
+    }
+
+    fn size(&self) -> IVec2 {
+        self.size
+    }
+
+    fn clip_rect(&self) -> Rect {
+        self.stack.clip(self.clip)
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.stack.push(rect, self.clip);
+    }
+
+    fn pop_clip(&mut self) {
+        self.stack.pop();
+    }
+
+    fn row(&self, y: i32) -> Option<&[u32]> {
+        match &self.target {
+            Target::Rows(pixels) => Some(&pixels[self.row_range(y)?]),
+            Target::Surface(surface) => surface.row(y),
+        }
+    }
+
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]> {
+        let range = self.row_range(y)?;
+        match &mut self.target {
+            Target::Rows(pixels) => Some(&mut pixels[range]),
+            Target::Surface(surface) => surface.row_mut(y),
+        }
+    }
+
+    fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    fn get_pixel_premul(&self, pos: IVec2) -> PremulColor {
+        let pixels = match &self.target {
+            Target::Rows(pixels) => pixels,
+            Target::Surface(surface) => return surface.get_premul(pos),
+        };
+        let pixel = pixels[self.index(pos)];
+        if self.premultiplied {
+            PremulColor::from_u32(pixel)
+        } else {
+            PremulColor::from_color(Color::from_u32(pixel))
+        }
+    }
+
+    fn set_pixel_premul(&mut self, pos: IVec2, color: PremulColor) {
+        let index = self.index(pos);
+        let pixels = match &mut self.target {
+            Target::Rows(pixels) => pixels,
+            Target::Surface(surface) => return surface.set_premul(pos, color),
+        };
+        pixels[index] = if self.premultiplied {
+            color.as_u32()
+        } else {
+            color.to_color().as_u32()
+        };
+    }
+}
+
+// Runs `draw` once per band on all available cores.
+pub fn render_parallel<S, F>(surface: &mut S, draw: F)
+where
+    S: Surface,
+    F: Fn(&mut Band) + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    render_bands(surface, threads, draw);
+}
+
+// Splits `surface` into `bands` horizontal bands and runs `draw` on each of
+// them on its own thread. Surfaces that don't expose their pixels, such as
+// views and masks, are drawn as a single band on the calling thread.
+pub fn render_bands<S, F>(surface: &mut S, bands: usize, draw: F)
+where
+    S: Surface,
+    F: Fn(&mut Band) + Sync,
+{
+    let size = surface.size();
+    let clip = surface.clip_rect();
+    let premultiplied = surface.is_premultiplied();
+    if size.x <= 0 || size.y <= 0 || clip.is_empty() {
+        return;
+    }
+    if surface.pixels_mut().is_none() {
+        let mut band = Band {
+            target: Target::Surface(surface),
+            size,
+            first_row: 0,
+            clip,
+            stack: ClipStack::new(),
+            premultiplied,
+        };
+        draw(&mut band);
+        return;
+    }
+    let pixels = surface.pixels_mut().unwrap();
+    let band_height = (size.y as usize).div_ceil(bands.max(1));
+    let draw = &draw;
+    thread::scope(|scope| {
+        for (index, pixels) in pixels.chunks_mut(band_height * size.x as usize).enumerate() {
+            let first_row = (index * band_height) as i32;
+            let rows = Rect::new(ivec2(0, first_row), ivec2(size.x, band_height as i32));
+            let clip = rows.intersect(clip);
+            if clip.is_empty() {
+                continue;
+            }
+            scope.spawn(move || {
+                let mut band = Band {
+                    target: Target::Rows(pixels),
+                    size,
+                    first_row,
+                    clip,
+                    stack: ClipStack::new(),
+                    premultiplied,
+                };
+                draw(&mut band);
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+    use crate::mask::Mask;
+    use crate::text::{BitmapFont, draw_text};
+
+    fn scene<S>(surface: &mut S, sprite: &Image)
+    where
+        S: Surface,
+    {
+        let translucent = Color::from_rgba(0x20, 0x80, 0xF0, 0x90);
+        surface.fill_rect(ivec2(-4, 3), ivec2(40, 25), Color::RED);
+        surface.fill_circle_aa(vec2(30.0, 30.0), 17.5, translucent);
+        surface.draw_line(ivec2(-10, 50), ivec2(70, -3), Color::GREEN);
+        let mut path = Path::new();
+        path.move_to(vec2(5.0, 60.0))
+            .cubic_to(vec2(20.0, 10.0), vec2(50.0, 90.0), vec2(60.0, 20.0))
+            .close();
+        surface.fill_path_aa(&path, translucent, FillRule::EvenOdd);
+        let options = BlitOptions {
+            blend: BlendMode::SourceOver,
+            filter: Filter::Bilinear,
+            ..Default::default()
+        };
+        surface.blit_with(
+            sprite,
+            None,
+            None,
+            Some(ivec2(7, 9)),
+            Some(ivec2(37, 29)),
+            options,
+        );
+        draw_text(
+            surface,
+            &BitmapFont::default(),
+            ivec2(2, 40),
+            Color::WHITE,
+            "bands",
+        );
+    }
+
+    fn sprite() -> Image {
+        let mut sprite = Image::new(9, 7, Color::TRANSPARENT);
+        for y in 0..7 {
+            for x in 0..9 {
+                let color =
+                    Color::from_rgba((x * 28) as u8, (y * 36) as u8, 0x80, (x * y * 5) as u8);
+                sprite.set_pixel(ivec2(x, y), color);
+            }
+        }
+        sprite
+    }
+
+    fn render(bands: usize, premultiplied: bool, clip: Option<Rect>) -> Image {
+        let sprite = sprite();
+        let mut image = Image::new(67, 61, Color::from_rgba(0x40, 0x40, 0x40, 0xC0));
+        if premultiplied {
+            image.premultiply();
+        }
+        if let Some(clip) = clip {
+            image.push_clip(clip);
+        }
+        render_bands(&mut image, bands, |band| scene(band, &sprite));
+        image
+    }
+
+    #[test]
+    fn bands_match_single_band() {
+        let clip = Rect::new(ivec2(5, 11), ivec2(50, 33));
+        for premultiplied in [false, true] {
+            for clip in [None, Some(clip)] {
+                let expected = render(1, premultiplied, clip);
+                for bands in [2, 3, 7, 61, 100] {
+                    let image = render(bands, premultiplied, clip);
+                    assert_eq!(image.pixels(), expected.pixels(), "{bands} bands");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_band_matches_serial() {
+        let sprite = sprite();
+        let mut expected = Image::new(67, 61, Color::BLACK);
+        scene(&mut expected, &sprite);
+        let mut image = Image::new(67, 61, Color::BLACK);
+        render_bands(&mut image, 1, |band| scene(band, &sprite));
+        assert_eq!(image.pixels(), expected.pixels());
+    }
+
+    #[test]
+    fn surfaces_without_pixels_render_serially() {
+        let sprite = sprite();
+        let region = Rect::new(ivec2(10, 5), ivec2(40, 50));
+
+        let mut expected = Image::new(67, 61, Color::BLACK);
+        scene(&mut expected.view_mut(region), &sprite);
+        let mut image = Image::new(67, 61, Color::BLACK);
+        render_bands(&mut image.view_mut(region), 4, |band| scene(band, &sprite));
+        assert_eq!(image.pixels(), expected.pixels());
+
+        let mut expected = Mask::new(67, 61, 0);
+        scene(&mut expected, &sprite);
+        let mut mask = Mask::new(67, 61, 0);
+        render_bands(&mut mask, 4, |band| scene(band, &sprite));
+        assert_eq!(mask.data(), expected.data());
+    }
+}
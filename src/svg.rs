@@ -0,0 +1,615 @@
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use crate::core::*;
+use crate::image::Image;
+
+#[derive(Debug)]
+pub enum SvgError {
+    Xml(roxmltree::Error),
+    NotSvg,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(error) => write!(f, "invalid SVG document: {error}"),
+            Self::NotSvg => f.write_str("root element is not <svg>"),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Xml(error) => Some(error),
+            Self::NotSvg => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for SvgError {
+    fn from(error: roxmltree::Error) -> Self {
+        Self::Xml(error)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Style {
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_style: StrokeStyle,
+    fill_rule: FillRule,
+    opacity: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(Color::BLACK),
+            stroke: None,
+            stroke_style: StrokeStyle::default(),
+            fill_rule: FillRule::NonZero,
+            opacity: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+        }
+    }
+}
+
+struct Shape {
+    path: Path,
+    transform: Affine2,
+    fill: Option<(Color, FillRule)>,
+    stroke: Option<(Color, StrokeStyle)>,
+}
+
+pub struct Svg {
+    shapes: Vec<Shape>,
+    view_box_pos: Vec2,
+    view_box_size: Vec2,
+    size: Vec2,
+}
+
+impl Svg {
+    pub fn parse(text: &str) -> Result<Self, SvgError> {
+        let document = Document::parse(text)?;
+        let root = document.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(SvgError::NotSvg);
+        }
+
+        let view_box = root
+            .attribute("viewBox")
+            .map(|v| Lexer::new(v).numbers())
+            .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0);
+        let width = root.attribute("width").and_then(parse_length);
+        let height = root.attribute("height").and_then(parse_length);
+        let (view_box_pos, view_box_size) = match &view_box {
+            Some(v) => (vec2(v[0], v[1]), vec2(v[2], v[3])),
+            None => (
+                Vec2::ZERO,
+                vec2(width.unwrap_or(100.0), height.unwrap_or(100.0)),
+            ),
+        };
+        let size = vec2(
+            width.unwrap_or(view_box_size.x),
+            height.unwrap_or(view_box_size.y),
+        );
+
+        let mut svg = Self {
+            shapes: Vec::new(),
+            view_box_pos,
+            view_box_size,
+            size,
+        };
+        svg.parse_children(root, Affine2::IDENTITY, Style::default());
+        Ok(svg)
+    }
+
+    pub fn open<P>(path: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::parse(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn render(&self, width: u32, height: u32) -> Image {
+        let mut image = Image::new(width, height, Color::TRANSPARENT);
+        let target = vec2(width as f32, height as f32);
+        let scale = (target / self.view_box_size).min_element();
+        let offset = (target - self.view_box_size * scale) / 2.0 - self.view_box_pos * scale;
+        let transform = Affine2::from_translation(offset) * Affine2::from_scale(Vec2::splat(scale));
+        self.render_to(&mut image, transform);
+        image
+    }
+
+    pub fn render_to<S>(&self, surface: &mut S, transform: Affine2)
+    where
+        S: Surface,
+    {
+        for shape in &self.shapes {
+            let transform = transform * shape.transform;
+            if let Some((color, rule)) = shape.fill {
+                let contours = shape.path.transform(&transform).flatten(0.25);
+                surface.fill_contours_aa(&contours, color, rule);
+            }
+            if let Some((color, style)) = shape.stroke {
+                let scale = transform.matrix2.determinant().abs().sqrt().max(1e-6);
+                let mut polygons = shape.path.stroke(&style, 0.25 / scale);
+                for point in polygons.iter_mut().flatten() {
+                    *point = transform.transform_point2(*point);
+                }
+                surface.fill_contours_aa(&polygons, color, FillRule::NonZero);
+            }
+        }
+    }
+
+    fn parse_children(&mut self, node: Node, transform: Affine2, style: Style) {
+        for child in node.children().filter(Node::is_element) {
+            if attribute(child, "display") == Some("none") {
+                continue;
+            }
+            let transform = match child.attribute("transform") {
+                Some(v) => transform * parse_transform(v),
+                None => transform,
+            };
+            let style = parse_style(child, style);
+            match child.tag_name().name() {
+                "g" | "svg" | "a" => self.parse_children(child, transform, style),
+                name => {
+                    if let Some(path) = parse_shape(child, name) {
+                        self.push_shape(path, transform, &style);
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_shape(&mut self, path: Path, transform: Affine2, style: &Style) {
+        let with_opacity = |color: Color, opacity: f32| {
+            color.with_a((color.a() as f32 * (opacity * style.opacity).clamp(0.0, 1.0)) as u8)
+        };
+        self.shapes.push(Shape {
+            path,
+            transform,
+            fill: style
+                .fill
+                .map(|c| (with_opacity(c, style.fill_opacity), style.fill_rule)),
+            stroke: style
+                .stroke
+                .filter(|_| style.stroke_style.width > 0.0)
+                .map(|c| (with_opacity(c, style.stroke_opacity), style.stroke_style)),
+        });
+    }
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    if let Some(style) = node.attribute("style") {
+        for declaration in style.split(';') {
+            if let Some((key, value)) = declaration.split_once(':')
+                && key.trim() == name
+            {
+                return Some(value.trim());
+            }
+        }
+    }
+    node.attribute(name).map(str::trim)
+}
+
+fn number_attribute(node: Node, name: &str) -> f32 {
+    attribute(node, name).and_then(parse_length).unwrap_or(0.0)
+}
+
+fn parse_style(node: Node, parent: Style) -> Style {
+    let mut style = parent;
+    if let Some(v) = attribute(node, "fill").and_then(parse_paint) {
+        style.fill = v;
+    }
+    if let Some(v) = attribute(node, "stroke").and_then(parse_paint) {
+        style.stroke = v;
+    }
+    if let Some(v) = attribute(node, "stroke-width").and_then(parse_length) {
+        style.stroke_style.width = v;
+    }
+    if let Some(v) = attribute(node, "stroke-miterlimit").and_then(parse_length) {
+        style.stroke_style.miter_limit = v;
+    }
+    match attribute(node, "stroke-linejoin") {
+        Some("miter") => style.stroke_style.join = LineJoin::Miter,
+        Some("round") => style.stroke_style.join = LineJoin::Round,
+        Some("bevel") => style.stroke_style.join = LineJoin::Bevel,
+        _ => {}
+    }
+    match attribute(node, "stroke-linecap") {
+        Some("butt") => style.stroke_style.cap = LineCap::Butt,
+        Some("round") => style.stroke_style.cap = LineCap::Round,
+        Some("square") => style.stroke_style.cap = LineCap::Square,
+        _ => {}
+    }
+    match attribute(node, "fill-rule") {
+        Some("nonzero") => style.fill_rule = FillRule::NonZero,
+        Some("evenodd") => style.fill_rule = FillRule::EvenOdd,
+        _ => {}
+    }
+    // Group opacity is approximated by multiplying it into the children.
+    let opacity = |name| attribute(node, name).and_then(parse_length);
+    style.opacity = parent.opacity * opacity("opacity").unwrap_or(1.0);
+    if let Some(v) = opacity("fill-opacity") {
+        style.fill_opacity = v;
+    }
+    if let Some(v) = opacity("stroke-opacity") {
+        style.stroke_opacity = v;
+    }
+    style
+}
+
+fn parse_shape(node: Node, name: &str) -> Option<Path> {
+    let mut path = Path::new();
+    let n = |name| number_attribute(node, name);
+    match name {
+        "path" => parse_path_data(node.attribute("d")?, &mut path),
+        "rect" => {
+            let (pos, size) = (vec2(n("x"), n("y")), vec2(n("width"), n("height")));
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return None;
+            }
+            let rx = attribute(node, "rx").and_then(parse_length);
+            let ry = attribute(node, "ry").and_then(parse_length);
+            let r = vec2(rx.or(ry).unwrap_or(0.0), ry.or(rx).unwrap_or(0.0))
+                .clamp(Vec2::ZERO, size / 2.0);
+            let end = pos + size;
+            if r.x > 0.0 && r.y > 0.0 {
+                path.move_to(vec2(pos.x + r.x, pos.y))
+                    .line_to(vec2(end.x - r.x, pos.y))
+                    .arc_to(r, 0.0, false, true, vec2(end.x, pos.y + r.y))
+                    .line_to(vec2(end.x, end.y - r.y))
+                    .arc_to(r, 0.0, false, true, vec2(end.x - r.x, end.y))
+                    .line_to(vec2(pos.x + r.x, end.y))
+                    .arc_to(r, 0.0, false, true, vec2(pos.x, end.y - r.y))
+                    .line_to(vec2(pos.x, pos.y + r.y))
+                    .arc_to(r, 0.0, false, true, vec2(pos.x + r.x, pos.y))
+                    .close();
+            } else {
+                path.move_to(pos)
+                    .line_to(vec2(end.x, pos.y))
+                    .line_to(end)
+                    .line_to(vec2(pos.x, end.y))
+                    .close();
+            }
+        }
+        "circle" | "ellipse" => {
+            let center = vec2(n("cx"), n("cy"));
+            let radii = if name == "circle" {
+                Vec2::splat(n("r"))
+            } else {
+                vec2(n("rx"), n("ry"))
+            };
+            if radii.x <= 0.0 || radii.y <= 0.0 {
+                return None;
+            }
+            path.move_to(center + vec2(radii.x, 0.0))
+                .arc_to(radii, 0.0, false, true, center - vec2(radii.x, 0.0))
+                .arc_to(radii, 0.0, false, true, center + vec2(radii.x, 0.0))
+                .close();
+        }
+        "line" => {
+            path.move_to(vec2(n("x1"), n("y1")))
+                .line_to(vec2(n("x2"), n("y2")));
+        }
+        "polyline" | "polygon" => {
+            let numbers = Lexer::new(node.attribute("points")?).numbers();
+            let mut points = numbers.chunks_exact(2).map(|p| vec2(p[0], p[1]));
+            path.move_to(points.next()?);
+            for point in points {
+                path.line_to(point);
+            }
+            if name == "polygon" {
+                path.close();
+            }
+        }
+        _ => return None,
+    }
+    Some(path)
+}
+
+// Units are ignored and percentages are not supported.
+fn parse_length(s: &str) -> Option<f32> {
+    if s.ends_with('%') {
+        return None;
+    }
+    Lexer::new(s).number()
+}
+
+fn parse_paint(s: &str) -> Option<Option<Color>> {
+    let s = s.trim();
+    if s == "none" {
+        return Some(None);
+    }
+    if s.starts_with('#') {
+        return Color::from_hex(s).ok().map(Some);
+    }
+    if let Some(args) = s.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let mut channels = args.split(',').map(|v| {
+            let v = v.trim();
+            match v.strip_suffix('%') {
+                Some(p) => p.trim().parse::<f32>().ok().map(|p| p * 2.55),
+                None => v.parse::<f32>().ok(),
+            }
+            .map(|v| v.round().clamp(0.0, 255.0) as u8)
+        });
+        let (r, g, b) = (channels.next()??, channels.next()??, channels.next()??);
+        return Some(Some(Color::from_rgb(r, g, b)));
+    }
+    let color = match s.to_ascii_lowercase().as_str() {
+        "black" | "currentcolor" => Color::BLACK,
+        "white" => Color::WHITE,
+        "red" => Color::RED,
+        "lime" => Color::GREEN,
+        "green" => Color::from_rgb(0x00, 0x80, 0x00),
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "cyan" | "aqua" => Color::CYAN,
+        "magenta" | "fuchsia" => Color::MAGENTA,
+        "gray" | "grey" => Color::GRAY,
+        "silver" => Color::LIGHT_GRAY,
+        "darkgray" | "darkgrey" => Color::from_rgb(0xA9, 0xA9, 0xA9),
+        "lightgray" | "lightgrey" => Color::from_rgb(0xD3, 0xD3, 0xD3),
+        "maroon" => Color::from_rgb(0x80, 0x00, 0x00),
+        "olive" => Color::from_rgb(0x80, 0x80, 0x00),
+        "navy" => Color::from_rgb(0x00, 0x00, 0x80),
+        "teal" => Color::from_rgb(0x00, 0x80, 0x80),
+        "purple" => Color::PURPLE,
+        "orange" => Color::ORANGE,
+        "brown" => Color::BROWN,
+        "pink" => Color::PINK,
+        "gold" => Color::from_rgb(0xFF, 0xD7, 0x00),
+        "transparent" => Color::TRANSPARENT,
+        _ => return None,
+    };
+    Some(Some(color))
+}
+
+fn parse_transform(s: &str) -> Affine2 {
+    let mut result = Affine2::IDENTITY;
+    for item in s.split(')') {
+        let Some((name, args)) = item.split_once('(') else {
+            continue;
+        };
+        let a = Lexer::new(args).numbers();
+        let arg = |i: usize, default: f32| a.get(i).copied().unwrap_or(default);
+        let transform = match name.trim_matches(|c: char| c.is_whitespace() || c == ',') {
+            "matrix" if a.len() == 6 => {
+                Affine2::from_cols_array(&[a[0], a[1], a[2], a[3], a[4], a[5]])
+            }
+            "translate" => Affine2::from_translation(vec2(arg(0, 0.0), arg(1, 0.0))),
+            "scale" => Affine2::from_scale(vec2(arg(0, 1.0), arg(1, arg(0, 1.0)))),
+            "rotate" => {
+                let center = vec2(arg(1, 0.0), arg(2, 0.0));
+                Affine2::from_translation(center)
+                    * Affine2::from_angle(arg(0, 0.0).to_radians())
+                    * Affine2::from_translation(-center)
+            }
+            "skewX" => {
+                Affine2::from_cols_array(&[1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0])
+            }
+            "skewY" => {
+                Affine2::from_cols_array(&[1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0])
+            }
+            _ => continue,
+        };
+        result *= transform;
+    }
+    result
+}
+
+fn parse_path_data(d: &str, path: &mut Path) {
+    let mut lexer = Lexer::new(d);
+    let mut command = b' ';
+    // Last control point of a cubic or quadratic segment, for S and T.
+    let mut last_cubic: Option<Vec2> = None;
+    let mut last_quad: Option<Vec2> = None;
+    loop {
+        lexer.skip_separators();
+        let Some(next) = lexer.peek() else {
+            break;
+        };
+        if next.is_ascii_alphabetic() {
+            command = next;
+            lexer.pos += 1;
+        } else if command == b'M' {
+            command = b'L';
+        } else if command == b'm' {
+            command = b'l';
+        }
+
+        let current = path.current_point();
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { current } else { Vec2::ZERO };
+        let mut point = || Some(origin + vec2(lexer.number()?, lexer.number()?));
+        let (cubic, quad) = match command.to_ascii_uppercase() {
+            b'M' => {
+                let Some(to) = point() else { break };
+                path.move_to(to);
+                (None, None)
+            }
+            b'L' => {
+                let Some(to) = point() else { break };
+                path.line_to(to);
+                (None, None)
+            }
+            b'H' => {
+                let Some(x) = lexer.number() else { break };
+                path.line_to(vec2(x + origin.x, current.y));
+                (None, None)
+            }
+            b'V' => {
+                let Some(y) = lexer.number() else { break };
+                path.line_to(vec2(current.x, y + origin.y));
+                (None, None)
+            }
+            b'C' => {
+                let (Some(c1), Some(c2), Some(to)) = (point(), point(), point()) else {
+                    break;
+                };
+                path.cubic_to(c1, c2, to);
+                (Some(c2), None)
+            }
+            b'S' => {
+                let (Some(c2), Some(to)) = (point(), point()) else {
+                    break;
+                };
+                let c1 = last_cubic.map_or(current, |c| 2.0 * current - c);
+                path.cubic_to(c1, c2, to);
+                (Some(c2), None)
+            }
+            b'Q' => {
+                let (Some(c), Some(to)) = (point(), point()) else {
+                    break;
+                };
+                path.quad_to(c, to);
+                (None, Some(c))
+            }
+            b'T' => {
+                let Some(to) = point() else { break };
+                let c = last_quad.map_or(current, |c| 2.0 * current - c);
+                path.quad_to(c, to);
+                (None, Some(c))
+            }
+            b'A' => {
+                let arc = (|| {
+                    let radii = vec2(lexer.number()?, lexer.number()?);
+                    let rotation = lexer.number()?;
+                    let large_arc = lexer.flag()?;
+                    let sweep = lexer.flag()?;
+                    let to = origin + vec2(lexer.number()?, lexer.number()?);
+                    Some((radii, rotation, large_arc, sweep, to))
+                })();
+                let Some((radii, rotation, large_arc, sweep, to)) = arc else {
+                    break;
+                };
+                path.arc_to(radii, rotation, large_arc, sweep, to);
+                (None, None)
+            }
+            b'Z' => {
+                path.close();
+                // Z takes no arguments, so a number after it is an error.
+                command = b' ';
+                (None, None)
+            }
+            _ => break,
+        };
+        last_cubic = cubic;
+        last_quad = quad;
+    }
+}
+
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            bytes: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(b) = self.peek() {
+            if !(b.is_ascii_whitespace() || b == b',') {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+        text.parse().ok()
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    fn numbers(&mut self) -> Vec<f32> {
+        std::iter::from_fn(|| self.number()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_data_stops_at_number_after_close() {
+        let mut path = Path::new();
+        parse_path_data("M0 0 L5 0 L5 5 Z 1", &mut path);
+        assert_eq!(path.bounds(), Some((vec2(0.0, 0.0), vec2(5.0, 5.0))));
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8">
+            <path d="M0 0 L5 0 L5 5 Z 1"/>
+        </svg>"#;
+        assert!(Svg::parse(svg).is_ok());
+    }
+
+    #[test]
+    fn path_data_continues_after_close() {
+        let mut path = Path::new();
+        parse_path_data("M0 0 L1 0 Z M2 2 L3 3 z L4 6", &mut path);
+        assert_eq!(path.bounds(), Some((vec2(0.0, 0.0), vec2(4.0, 6.0))));
+    }
+}
@@ -0,0 +1,19 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl TextAlign {
+    // Horizontal offset of a line of `width` relative to the anchor point.
+    pub fn offset(self, width: f32) -> f32 {
+        match self {
+            Self::Left | Self::Justify => 0.0,
+            Self::Center => -width / 2.0,
+            Self::Right => -width,
+        }
+    }
+}
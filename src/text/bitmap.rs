@@ -0,0 +1,243 @@
+use crate::core::*;
+use crate::image::Image;
+
+use super::Font;
+use super::default_font::GLYPHS;
+
+const TAB_WIDTH: i32 = 4;
+
+pub struct BitmapFont {
+    image: Image,
+    glyph_size: IVec2,
+    columns: i32,
+    first_char: u32,
+    count: u32,
+    spacing: IVec2,
+}
+
+impl BitmapFont {
+    pub fn from_image(image: Image, glyph_size: IVec2, first_char: char) -> Self {
+        assert!(glyph_size.x > 0 && glyph_size.y > 0);
+        let grid = image.size() / glyph_size;
+        Self {
+            image,
+            glyph_size,
+            columns: grid.x,
+            first_char: first_char as u32,
+            count: (grid.x * grid.y) as u32,
+            spacing: IVec2::ZERO,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: IVec2) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn glyph_size(&self) -> IVec2 {
+        self.glyph_size
+    }
+
+    pub fn advance(&self) -> i32 {
+        self.glyph_size.x + self.spacing.x
+    }
+
+    pub fn line_height(&self) -> i32 {
+        self.glyph_size.y + self.spacing.y
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn glyph_pos(&self, ch: char) -> Option<IVec2> {
+        let index = (ch as u32).checked_sub(self.first_char)?;
+        if index >= self.count {
+            return None;
+        }
+        let index = index as i32;
+        Some(ivec2(index % self.columns, index / self.columns) * self.glyph_size)
+    }
+
+    pub fn measure(&self, text: &str) -> IVec2 {
+        let mut size = IVec2::ZERO;
+        for line in text.lines() {
+            let columns = line_columns(line);
+            if columns > 0 {
+                size.x = size.x.max(columns * self.advance() - self.spacing.x);
+            }
+            size.y += self.line_height();
+        }
+        if text.ends_with('\n') {
+            size.y += self.line_height();
+        }
+        size
+    }
+}
+
+impl Default for BitmapFont {
+    fn default() -> Self {
+        const COLUMNS: i32 = 16;
+        let glyph_size = ivec2(6, 10);
+        let rows = (GLYPHS.len() as i32 + COLUMNS - 1) / COLUMNS;
+        let sheet = glyph_size * ivec2(COLUMNS, rows);
+        let mut image = Image::new(sheet.x as u32, sheet.y as u32, Color::TRANSPARENT);
+        for (index, glyph) in GLYPHS.iter().enumerate() {
+            let origin = ivec2(index as i32 % COLUMNS, index as i32 / COLUMNS) * glyph_size;
+            for (y, bits) in glyph.iter().enumerate() {
+                for x in 0..glyph_size.x {
+                    if bits & (0x80 >> x) != 0 {
+                        image.set_pixel(origin + ivec2(x, y as i32), Color::WHITE);
+                    }
+                }
+            }
+        }
+        Self::from_image(image, glyph_size, ' ')
+    }
+}
+
+impl Font for BitmapFont {
+    fn line_height(&self) -> f32 {
+        self.line_height() as f32
+    }
+
+    fn advance(&self, _ch: char) -> f32 {
+        self.advance() as f32
+    }
+
+    fn draw_glyph<S>(&self, surface: &mut S, ch: char, pos: Vec2, color: Color)
+    where
+        S: Surface,
+    {
+        if let Some(src_pos) = self.glyph_pos(ch).or_else(|| self.glyph_pos('?')) {
+            let options = BlitOptions {
+                blend: BlendMode::SourceOver,
+                tint: Some(color),
+                ..Default::default()
+            };
+            surface.blit_with(
+                &self.image,
+                Some(src_pos),
+                Some(self.glyph_size),
+                Some(pos.round().as_ivec2()),
+                None,
+                options,
+            );
+        }
+    }
+}
+
+fn line_columns(line: &str) -> i32 {
+    line.chars().fold(0, |columns, ch| match ch {
+        '\t' => (columns / TAB_WIDTH + 1) * TAB_WIDTH,
+        '\r' => columns,
+        _ => columns + 1,
+    })
+}
+
+pub fn draw_text<S>(surface: &mut S, font: &BitmapFont, pos: IVec2, color: Color, text: &str)
+where
+    S: Surface,
+{
+    let mut pen = pos;
+    let mut column = 0;
+    for ch in text.chars() {
+        match ch {
+            '\n' => {
+                pen = ivec2(pos.x, pen.y + font.line_height());
+                column = 0;
+                continue;
+            }
+            '\r' => continue,
+            '\t' => {
+                let next = (column / TAB_WIDTH + 1) * TAB_WIDTH;
+                pen.x += (next - column) * font.advance();
+                column = next;
+                continue;
+            }
+            _ => {}
+        }
+        font.draw_glyph(surface, ch, pen.as_vec2(), color);
+        pen.x += font.advance();
+        column += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> BitmapFont {
+        BitmapFont::default().with_spacing(ivec2(1, 2))
+    }
+
+    fn render(text: &str) -> Image {
+        let mut image = Image::new(80, 48, Color::TRANSPARENT);
+        draw_text(&mut image, &font(), IVec2::ZERO, Color::WHITE, text);
+        image
+    }
+
+    // The smallest rectangle holding every drawn pixel.
+    fn ink(image: &Image) -> Rect {
+        let (mut min, mut max) = (image.size(), IVec2::ZERO);
+        for y in 0..image.size().y {
+            for x in 0..image.size().x {
+                if image.get_pixel(ivec2(x, y)).a() != 0 {
+                    min = min.min(ivec2(x, y));
+                    max = max.max(ivec2(x + 1, y + 1));
+                }
+            }
+        }
+        Rect::from_min_max(min, max.max(min))
+    }
+
+    #[test]
+    fn measure() {
+        let font = font();
+        assert_eq!((font.advance(), font.line_height()), (7, 12));
+        let cases = [
+            ("", ivec2(0, 0)),
+            ("abc", ivec2(20, 12)),
+            ("ab\nabcd", ivec2(27, 24)),
+            ("abcd\nab", ivec2(27, 24)),
+            // A trailing newline starts another, empty line.
+            ("ab\n", ivec2(13, 24)),
+            ("ab\r\n", ivec2(13, 24)),
+            ("\n", ivec2(0, 24)),
+            ("\n\n", ivec2(0, 36)),
+            ("ab\n\ncd", ivec2(13, 36)),
+            // Tabs advance to the next multiple of four columns.
+            ("\tx", ivec2(34, 12)),
+            ("abc\tx", ivec2(34, 12)),
+            ("abcd\tx", ivec2(62, 12)),
+            ("ab\t", ivec2(27, 12)),
+            // Missing glyphs still take a column.
+            ("a\u{2603}b", ivec2(20, 12)),
+        ];
+        for (text, size) in cases {
+            assert_eq!(font.measure(text), size, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn draw_text_stays_within_measure() {
+        for text in ["abc", "Wy\ngj", "\tx\n_|", "ab\n", "a\u{2603}\n\tQ"] {
+            let ink = ink(&render(text));
+            let size = font().measure(text);
+            assert!(!ink.is_empty(), "{text:?}");
+            assert!(ink.max().cmple(size).all(), "{text:?}: {ink:?} {size}");
+        }
+    }
+
+    #[test]
+    fn draw_text_layout() {
+        let same = |a: &Image, b: &Image| a.pixels() == b.pixels();
+        let mut pieces = Image::new(80, 48, Color::TRANSPARENT);
+        for (pos, text) in [(ivec2(0, 0), "a"), (ivec2(28, 0), "b"), (ivec2(0, 12), "c")] {
+            draw_text(&mut pieces, &font(), pos, Color::WHITE, text);
+        }
+        assert!(same(&render("a\tb\r\nc"), &pieces));
+        assert!(same(&render("ab\n"), &render("ab")));
+        assert!(same(&render("x\u{2603}"), &render("x?")));
+    }
+}
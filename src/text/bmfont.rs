@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::core::*;
+use crate::image::Image;
+
+use super::Font;
+
+const MAX_PAGES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BmFontError {
+    InvalidText { line: usize },
+    InvalidBinary,
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for BmFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidText { line } => write!(f, "invalid BMFont descriptor at line {line}"),
+            Self::InvalidBinary => f.write_str("truncated or malformed binary BMFont descriptor"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported binary BMFont version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BmFontError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BmGlyph {
+    pub src_pos: IVec2,
+    pub size: IVec2,
+    pub offset: IVec2,
+    pub advance: i32,
+    pub page: usize,
+}
+
+pub struct BmFont {
+    line_height: i32,
+    base: i32,
+    glyphs: HashMap<char, BmGlyph>,
+    kernings: HashMap<(char, char), i32>,
+    page_files: Vec<String>,
+    pages: Vec<Image>,
+}
+
+impl BmFont {
+    pub fn parse(data: &[u8]) -> Result<Self, BmFontError> {
+        let mut font = Self {
+            line_height: 0,
+            base: 0,
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+            page_files: Vec::new(),
+            pages: Vec::new(),
+        };
+        if let Some(data) = data.strip_prefix(b"BMF") {
+            font.parse_binary(data)?;
+        } else {
+            let text =
+                std::str::from_utf8(data).map_err(|_| BmFontError::InvalidText { line: 1 })?;
+            font.parse_text(text)?;
+        }
+        Ok(font)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn open<P>(path: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref();
+        let font = Self::parse(&std::fs::read(path).unwrap()).unwrap();
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        let pages = font
+            .page_files
+            .iter()
+            .map(|file| Image::open(dir.join(file)))
+            .collect();
+        font.with_pages(pages)
+    }
+
+    pub fn with_pages(mut self, pages: Vec<Image>) -> Self {
+        self.pages = pages;
+        self
+    }
+
+    pub fn page_files(&self) -> &[String] {
+        &self.page_files
+    }
+
+    pub fn pages(&self) -> &[Image] {
+        &self.pages
+    }
+
+    pub fn line_height(&self) -> i32 {
+        self.line_height
+    }
+
+    pub fn base(&self) -> i32 {
+        self.base
+    }
+
+    pub fn glyph(&self, ch: char) -> Option<&BmGlyph> {
+        self.glyphs.get(&ch)
+    }
+
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    pub fn measure(&self, text: &str) -> IVec2 {
+        let mut size = IVec2::ZERO;
+        for line in text.split('\n') {
+            let mut width = 0;
+            self.layout_line(line, |_, _, pen| width = width.max(pen));
+            size.x = size.x.max(width);
+            size.y = size.y.saturating_add(self.line_height);
+        }
+        size
+    }
+
+    // Calls `glyph` with every glyph of `line`, its pen position and the pen
+    // position after its advance.
+    fn layout_line<F>(&self, line: &str, mut glyph: F)
+    where
+        F: FnMut(&BmGlyph, i32, i32),
+    {
+        let mut pen = 0i32;
+        let mut prev = None;
+        for ch in line.chars().filter(|ch| *ch != '\r') {
+            let Some(g) = self.glyph(ch).or_else(|| self.glyph('?')) else {
+                continue;
+            };
+            if let Some(prev) = prev {
+                pen = pen.saturating_add(self.kerning(prev, ch));
+            }
+            let next = pen.saturating_add(g.advance);
+            glyph(g, pen, next);
+            pen = next;
+            prev = Some(ch);
+        }
+    }
+
+    fn blit_glyph<S>(&self, surface: &mut S, glyph: &BmGlyph, pen: IVec2, color: Color)
+    where
+        S: Surface,
+    {
+        let Some(page) = self.pages.get(glyph.page) else {
+            return;
+        };
+        if glyph.size.x <= 0 || glyph.size.y <= 0 {
+            return;
+        }
+        let options = BlitOptions {
+            blend: BlendMode::SourceOver,
+            tint: Some(color),
+            ..Default::default()
+        };
+        surface.blit_with(
+            page,
+            Some(glyph.src_pos),
+            Some(glyph.size),
+            Some(pen + glyph.offset),
+            None,
+            options,
+        );
+    }
+
+    fn add_glyph(&mut self, id: u32, glyph: BmGlyph) {
+        if let Some(ch) = char::from_u32(id) {
+            self.glyphs.insert(ch, glyph);
+        }
+    }
+
+    fn add_kerning(&mut self, first: u32, second: u32, amount: i32) {
+        if let (Some(first), Some(second)) = (char::from_u32(first), char::from_u32(second)) {
+            self.kernings.insert((first, second), amount);
+        }
+    }
+
+    fn parse_text(&mut self, text: &str) -> Result<(), BmFontError> {
+        let mut page_count = 0;
+        for (index, line) in text.lines().enumerate() {
+            let error = BmFontError::InvalidText { line: index + 1 };
+            let Some((tag, attrs)) = parse_tag(line) else {
+                continue;
+            };
+            let attrs = attrs.ok_or(error)?;
+            let get = |key: &str| -> Result<i32, BmFontError> {
+                attrs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .and_then(|(_, v)| v.parse().ok())
+                    .ok_or(error)
+            };
+            let get_index = |key: &str, count: usize| -> Result<usize, BmFontError> {
+                usize::try_from(get(key)?)
+                    .ok()
+                    .filter(|&index| index < count)
+                    .ok_or(error)
+            };
+            match tag {
+                "common" => {
+                    self.line_height = get("lineHeight")?;
+                    self.base = get("base")?;
+                    // Page ids are a single byte in the binary format.
+                    page_count = get_index("pages", MAX_PAGES + 1)?;
+                }
+                "page" => {
+                    let id = get_index("id", page_count)?;
+                    let file = attrs.iter().find(|(k, _)| *k == "file").ok_or(error)?.1;
+                    if self.page_files.len() <= id {
+                        self.page_files.resize(id + 1, String::new());
+                    }
+                    self.page_files[id] = file.to_string();
+                }
+                "char" => {
+                    let glyph = BmGlyph {
+                        src_pos: ivec2(get("x")?, get("y")?),
+                        size: ivec2(get("width")?, get("height")?),
+                        offset: ivec2(get("xoffset")?, get("yoffset")?),
+                        advance: get("xadvance")?,
+                        page: get("page").unwrap_or(0) as usize,
+                    };
+                    self.add_glyph(get("id")? as u32, glyph);
+                }
+                "kerning" => {
+                    self.add_kerning(get("first")? as u32, get("second")? as u32, get("amount")?);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_binary(&mut self, data: &[u8]) -> Result<(), BmFontError> {
+        let (&version, mut data) = data.split_first().ok_or(BmFontError::InvalidBinary)?;
+        if version != 3 {
+            return Err(BmFontError::UnsupportedVersion(version));
+        }
+        while !data.is_empty() {
+            let mut reader = Reader(data);
+            let kind = reader.u8()?;
+            let len = reader.u32()? as usize;
+            let block = reader.take(len)?;
+            data = reader.0;
+            let mut reader = Reader(block);
+            match kind {
+                2 => {
+                    self.line_height = reader.u16()? as i32;
+                    self.base = reader.u16()? as i32;
+                }
+                3 => {
+                    self.page_files = block
+                        .split(|b| *b == 0)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .collect();
+                }
+                4 => {
+                    while !reader.0.is_empty() {
+                        let id = reader.u32()?;
+                        let src_pos = ivec2(reader.u16()? as i32, reader.u16()? as i32);
+                        let size = ivec2(reader.u16()? as i32, reader.u16()? as i32);
+                        let offset = ivec2(reader.i16()? as i32, reader.i16()? as i32);
+                        let advance = reader.i16()? as i32;
+                        let page = reader.u8()? as usize;
+                        reader.u8()?;
+                        let glyph = BmGlyph {
+                            src_pos,
+                            size,
+                            offset,
+                            advance,
+                            page,
+                        };
+                        self.add_glyph(id, glyph);
+                    }
+                }
+                5 => {
+                    while !reader.0.is_empty() {
+                        let first = reader.u32()?;
+                        let second = reader.u32()?;
+                        let amount = reader.i16()? as i32;
+                        self.add_kerning(first, second, amount);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Font for BmFont {
+    fn line_height(&self) -> f32 {
+        self.line_height as f32
+    }
+
+    fn advance(&self, ch: char) -> f32 {
+        self.glyph(ch)
+            .or_else(|| self.glyph('?'))
+            .map_or(0.0, |glyph| glyph.advance as f32)
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        self.kerning(first, second) as f32
+    }
+
+    fn draw_glyph<S>(&self, surface: &mut S, ch: char, pos: Vec2, color: Color)
+    where
+        S: Surface,
+    {
+        if let Some(glyph) = self.glyph(ch).or_else(|| self.glyph('?')) {
+            self.blit_glyph(surface, glyph, pos.round().as_ivec2(), color);
+        }
+    }
+}
+
+type Attrs<'a> = Vec<(&'a str, &'a str)>;
+
+// Splits a `tag key=value key="quoted value"` line. Returns `None` for blank
+// lines and `Some((tag, None))` when the attributes are malformed.
+fn parse_tag(line: &str) -> Option<(&str, Option<Attrs<'_>>)> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    if tag.is_empty() {
+        return None;
+    }
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some((tag, Some(attrs)));
+        }
+        let Some((key, value)) = rest.split_once('=') else {
+            return Some((tag, None));
+        };
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some(split) => split,
+                None => return Some((tag, None)),
+            },
+            None => value.split_once(' ').unwrap_or((value, "")),
+        };
+        attrs.push((key, value));
+        rest = next;
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BmFontError> {
+        if self.0.len() < len {
+            return Err(BmFontError::InvalidBinary);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, BmFontError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BmFontError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, BmFontError> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, BmFontError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+pub fn draw_bmfont_text<S>(surface: &mut S, font: &BmFont, pos: IVec2, color: Color, text: &str)
+where
+    S: Surface,
+{
+    let mut y = pos.y;
+    for line in text.split('\n') {
+        font.layout_line(line, |glyph, pen, _| {
+            font.blit_glyph(surface, glyph, ivec2(pos.x + pen, y), color);
+        });
+        y += font.line_height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMON: &str = "common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=2\n";
+
+    fn parse(pages: &str) -> Result<BmFont, BmFontError> {
+        BmFont::parse(format!("{COMMON}{pages}").as_bytes())
+    }
+
+    #[test]
+    fn text_pages() {
+        let font = parse("page id=1 file=\"b.png\"\npage id=0 file=\"a.png\"\n").unwrap();
+        assert_eq!(font.page_files(), ["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn text_page_id_out_of_range() {
+        let error = Some(BmFontError::InvalidText { line: 2 });
+        assert_eq!(parse("page id=-1 file=\"a.png\"\n").err(), error);
+        assert_eq!(parse("page id=2 file=\"a.png\"\n").err(), error);
+        assert_eq!(parse("page id=2147483647 file=\"a.png\"\n").err(), error);
+    }
+
+    #[test]
+    fn text_page_count_out_of_range() {
+        let text = "common lineHeight=10 base=8 pages=100000\npage id=99999 file=\"a.png\"\n";
+        assert_eq!(
+            BmFont::parse(text.as_bytes()).err(),
+            Some(BmFontError::InvalidText { line: 1 })
+        );
+    }
+
+    const CHARS: &str = "\
+chars count=2
+char id=65 x=1 y=2 width=5 height=7 xoffset=-1 yoffset=3 xadvance=6 page=1 chnl=15
+char id=66   x=8 y=2 width=4 height=7 xoffset=0 yoffset=3 xadvance=5 page=0 chnl=15
+
+kernings count=2
+kerning first=65 second=66 amount=-2
+kerning first=66 second=65 amount=1
+";
+
+    #[test]
+    fn text_chars_and_kernings() {
+        let font = parse(CHARS).unwrap();
+        assert_eq!((font.line_height(), font.base()), (10, 8));
+        let a = BmGlyph {
+            src_pos: ivec2(1, 2),
+            size: ivec2(5, 7),
+            offset: ivec2(-1, 3),
+            advance: 6,
+            page: 1,
+        };
+        assert_eq!(font.glyph('A'), Some(&a));
+        assert_eq!(font.glyph('B').unwrap().src_pos, ivec2(8, 2));
+        assert_eq!(font.glyph('C'), None);
+        assert_eq!(font.kerning('A', 'B'), -2);
+        assert_eq!(font.kerning('B', 'A'), 1);
+        assert_eq!(font.kerning('A', 'A'), 0);
+        assert_eq!(font.measure("AB"), ivec2(9, 10));
+        assert_eq!(font.measure("BA\nA"), ivec2(12, 20));
+    }
+
+    #[test]
+    fn text_errors() {
+        let error = |line| Some(BmFontError::InvalidText { line });
+        // Missing, malformed and out of range attributes.
+        assert_eq!(parse("char id=65 x=1 y=2\n").err(), error(2));
+        assert_eq!(parse("\nchar id=65 x=one\n").err(), error(3));
+        assert_eq!(parse("kerning first=65 second=66\n").err(), error(2));
+        assert_eq!(
+            parse("kerning first=65 second=66 amount=99999999999\n").err(),
+            error(2)
+        );
+        assert_eq!(parse("page id=0 file=\"a.png\n").err(), error(2));
+        assert_eq!(parse("page id=0\n").err(), error(2));
+        assert_eq!(parse("char id=65 x\n").err(), error(2));
+        assert_eq!(BmFont::parse(b"common lineHeight=10\n").err(), error(1));
+        // Pages can't be declared before their count.
+        assert_eq!(BmFont::parse(b"page id=0 file=\"a.png\"\n").err(), error(1));
+        assert_eq!(BmFont::parse(b"info face=\"\xFF\"\n").err(), error(1));
+
+        // Unknown tags and blank lines are skipped.
+        assert!(parse("info face=\"Arial\" size=32\n\n   \nscaleW=1\n").is_ok());
+    }
+
+    // A binary descriptor built from `(kind, contents)` blocks.
+    fn binary(blocks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut data = b"BMF\x03".to_vec();
+        for (kind, contents) in blocks {
+            data.push(*kind);
+            data.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            data.extend_from_slice(contents);
+        }
+        data
+    }
+
+    fn char_record(id: u32, x: u16, advance: i16, page: u8) -> Vec<u8> {
+        let mut record = id.to_le_bytes().to_vec();
+        for v in [x, 2, 5, 7] {
+            record.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [-1i16, 3, advance] {
+            record.extend_from_slice(&v.to_le_bytes());
+        }
+        record.extend_from_slice(&[page, 15]);
+        record
+    }
+
+    fn kerning_record(first: u32, second: u32, amount: i16) -> Vec<u8> {
+        let mut record = first.to_le_bytes().to_vec();
+        record.extend_from_slice(&second.to_le_bytes());
+        record.extend_from_slice(&amount.to_le_bytes());
+        record
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut common = 10u16.to_le_bytes().to_vec();
+        common.extend_from_slice(&8u16.to_le_bytes());
+        common.extend_from_slice(&[0; 11]);
+        binary(&[
+            (1, b"\x20\x00\x00\x00Arial\x00".to_vec()),
+            (2, common),
+            (3, b"a.png\x00b.png\x00".to_vec()),
+            (
+                4,
+                [char_record(65, 1, 6, 1), char_record(66, 8, 5, 0)].concat(),
+            ),
+            (
+                5,
+                [kerning_record(65, 66, -2), kerning_record(66, 65, 1)].concat(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn binary_chars_and_kernings() {
+        let font = BmFont::parse(&sample()).unwrap();
+        assert_eq!((font.line_height(), font.base()), (10, 8));
+        assert_eq!(font.page_files(), ["a.png", "b.png"]);
+        let a = BmGlyph {
+            src_pos: ivec2(1, 2),
+            size: ivec2(5, 7),
+            offset: ivec2(-1, 3),
+            advance: 6,
+            page: 1,
+        };
+        assert_eq!(font.glyph('A'), Some(&a));
+        assert_eq!(font.glyph('B').unwrap().src_pos, ivec2(8, 2));
+        assert_eq!(font.kerning('A', 'B'), -2);
+        assert_eq!(font.kerning('B', 'A'), 1);
+        assert_eq!(font.measure("AB"), ivec2(9, 10));
+    }
+
+    #[test]
+    fn binary_errors() {
+        let invalid = Some(BmFontError::InvalidBinary);
+        assert_eq!(BmFont::parse(b"BMF").err(), invalid);
+        assert_eq!(
+            BmFont::parse(b"BMF\x02").err(),
+            Some(BmFontError::UnsupportedVersion(2))
+        );
+        // Truncated block headers and contents.
+        assert_eq!(BmFont::parse(b"BMF\x03\x04").err(), invalid);
+        assert_eq!(BmFont::parse(b"BMF\x03\x04\x01\x00").err(), invalid);
+        assert_eq!(
+            BmFont::parse(b"BMF\x03\x04\x10\x00\x00\x00\x00").err(),
+            invalid
+        );
+        assert_eq!(BmFont::parse(b"BMF\x03\x04\xFF\xFF\xFF\xFF").err(), invalid);
+        // Blocks whose length isn't a whole number of records.
+        let mut chars = char_record(65, 1, 6, 0);
+        chars.pop();
+        assert_eq!(BmFont::parse(&binary(&[(4, chars)])).err(), invalid);
+        let mut kernings = kerning_record(65, 66, 1);
+        kernings.push(0);
+        assert_eq!(BmFont::parse(&binary(&[(5, kernings)])).err(), invalid);
+        assert_eq!(BmFont::parse(&binary(&[(2, vec![10])])).err(), invalid);
+
+        // Unknown and empty blocks are skipped.
+        assert!(BmFont::parse(&binary(&[(9, vec![1, 2, 3]), (4, vec![])])).is_ok());
+    }
+
+    #[test]
+    fn truncated_and_corrupted_data_never_panics() {
+        let text = format!("{COMMON}page id=1 file=\"b.png\"\n{CHARS}");
+        for data in [sample(), text.into_bytes()] {
+            for len in 0..data.len() {
+                let _ = BmFont::parse(&data[..len]);
+            }
+            let mut state = 0x2545F491u32;
+            for _ in 0..2000 {
+                let mut data = data.clone();
+                for _ in 0..4 {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let index = state as usize % data.len();
+                    data[index] = (state >> 24) as u8;
+                }
+                if let Ok(font) = BmFont::parse(&data) {
+                    font.measure("ABBA\n?A");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_metrics_saturate() {
+        let font = parse(
+            "char id=65 x=0 y=0 width=1 height=1 xoffset=0 yoffset=0 xadvance=2147483647\n\
+             kerning first=65 second=65 amount=2147483647\n",
+        )
+        .unwrap();
+        assert_eq!(font.measure("AAA").x, i32::MAX);
+        let font = BmFont::parse(b"common lineHeight=2147483647 base=0 pages=0\n").unwrap();
+        assert_eq!(font.measure("\n\n").y, i32::MAX);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::core::*;
+
+pub trait Font {
+    fn line_height(&self) -> f32;
+    fn advance(&self, ch: char) -> f32;
+
+    fn kerning(&self, _first: char, _second: char) -> f32 {
+        0.0
+    }
+
+    // Draws `ch` with its line cell's top-left corner at `pos`.
+    fn draw_glyph<S>(&self, surface: &mut S, ch: char, pos: Vec2, color: Color)
+    where
+        S: Surface;
+}
+
+impl<F> Font for &F
+where
+    F: Font + ?Sized,
+{
+    fn line_height(&self) -> f32 {
+        (**self).line_height()
+    }
+
+    fn advance(&self, ch: char) -> f32 {
+        (**self).advance(ch)
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        (**self).kerning(first, second)
+    }
+
+    fn draw_glyph<S>(&self, surface: &mut S, ch: char, pos: Vec2, color: Color)
+    where
+        S: Surface,
+    {
+        (**self).draw_glyph(surface, ch, pos, color);
+    }
+}
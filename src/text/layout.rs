@@ -0,0 +1,342 @@
+use std::ops::Range;
+
+use crate::core::*;
+
+use super::{Font, TextAlign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LayoutOptions {
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+    pub line_spacing: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub ch: char,
+    pub pos: Vec2,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBox {
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub glyphs: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextLayout {
+    glyphs: Vec<PositionedGlyph>,
+    lines: Vec<LineBox>,
+    size: Vec2,
+}
+
+// A wrapped line before alignment, and whether it ends its paragraph.
+struct Line {
+    chars: Vec<(char, Color)>,
+    width: f32,
+    last: bool,
+}
+
+impl TextLayout {
+    pub fn new<F>(font: &F, text: &str, color: Color, options: &LayoutOptions) -> Self
+    where
+        F: Font,
+    {
+        let chars: Vec<_> = text.chars().map(|ch| (ch, color)).collect();
+        Self::from_chars(font, &chars, options)
+    }
+
+    // Like `new`, but `[color=#RRGGBB]...[/color]` changes the color of the
+    // enclosed text and `[[` produces a literal `[`.
+    pub fn from_markup<F>(font: &F, markup: &str, color: Color, options: &LayoutOptions) -> Self
+    where
+        F: Font,
+    {
+        Self::from_chars(font, &parse_markup(markup, color), options)
+    }
+
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    pub fn lines(&self) -> &[LineBox] {
+        &self.lines
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn draw<S, F>(&self, surface: &mut S, font: &F, pos: Vec2)
+    where
+        S: Surface,
+        F: Font,
+    {
+        for glyph in &self.glyphs {
+            font.draw_glyph(surface, glyph.ch, pos + glyph.pos, glyph.color);
+        }
+    }
+
+    fn from_chars<F>(font: &F, chars: &[(char, Color)], options: &LayoutOptions) -> Self
+    where
+        F: Font,
+    {
+        // A width that can't fit anything means no wrapping.
+        let max_width = options.max_width.filter(|&width| width > 0.0);
+        let lines = wrap(font, chars, max_width);
+        let width =
+            max_width.unwrap_or_else(|| lines.iter().fold(0.0, |w, line| line.width.max(w)));
+        let line_height = font.line_height();
+        let mut layout = Self::default();
+        for (index, line) in lines.iter().enumerate() {
+            let y = index as f32 * (line_height + options.line_spacing);
+            let spaces = line.chars.iter().filter(|(ch, _)| *ch == ' ').count();
+            let justify = options.align == TextAlign::Justify && !line.last && spaces > 0;
+            let slack = width - line.width;
+            let (x, gap) = match options.align {
+                TextAlign::Center => (slack / 2.0, 0.0),
+                TextAlign::Right => (slack, 0.0),
+                _ if justify => (0.0, slack / spaces as f32),
+                _ => (0.0, 0.0),
+            };
+            let start = layout.glyphs.len();
+            let mut pen = x;
+            let mut prev = None;
+            for &(ch, color) in &line.chars {
+                if let Some(prev) = prev {
+                    pen += font.kerning(prev, ch);
+                }
+                if !ch.is_whitespace() {
+                    layout.glyphs.push(PositionedGlyph {
+                        ch,
+                        pos: vec2(pen, y),
+                        color,
+                    });
+                }
+                pen += font.advance(ch);
+                if ch == ' ' {
+                    pen += gap;
+                }
+                prev = Some(ch);
+            }
+            layout.lines.push(LineBox {
+                pos: vec2(x, y),
+                size: vec2(pen - x, line_height),
+                glyphs: start..layout.glyphs.len(),
+            });
+            layout.size = vec2(width, y + line_height);
+        }
+        layout
+    }
+}
+
+fn width<F>(font: &F, prev: Option<char>, chars: &[(char, Color)]) -> f32
+where
+    F: Font,
+{
+    let mut prev = prev;
+    let mut width = 0.0;
+    for &(ch, _) in chars {
+        if let Some(prev) = prev {
+            width += font.kerning(prev, ch);
+        }
+        width += font.advance(ch);
+        prev = Some(ch);
+    }
+    width
+}
+
+fn make_line<F>(font: &F, mut chars: Vec<(char, Color)>, last: bool) -> Line
+where
+    F: Font,
+{
+    while chars.last().is_some_and(|(ch, _)| ch.is_whitespace()) {
+        chars.pop();
+    }
+    Line {
+        width: width(font, None, &chars),
+        chars,
+        last,
+    }
+}
+
+// Greedily breaks every paragraph at spaces so that lines fit `max_width`,
+// splitting words that are wider than a whole line. Whitespace at the start of
+// a line is dropped.
+fn wrap<F>(font: &F, chars: &[(char, Color)], max_width: Option<f32>) -> Vec<Line>
+where
+    F: Font,
+{
+    let max_width = max_width.unwrap_or(f32::INFINITY);
+    let mut lines = Vec::new();
+    for paragraph in chars.split(|(ch, _)| *ch == '\n') {
+        let mut line = Vec::new();
+        let mut line_width = 0.0;
+        let mut rest = paragraph;
+        while !rest.is_empty() {
+            let spaces = rest.iter().take_while(|(ch, _)| ch.is_whitespace()).count();
+            if line.is_empty() && spaces > 0 {
+                rest = &rest[spaces..];
+                continue;
+            }
+            let len = rest[spaces..]
+                .iter()
+                .take_while(|(ch, _)| !ch.is_whitespace())
+                .count();
+            let (word, next) = rest.split_at(spaces + len);
+            let added = width(font, line.last().map(|(ch, _)| *ch), word);
+            if line_width + added <= max_width {
+                line.extend_from_slice(word);
+                line_width += added;
+                rest = next;
+            } else if !line.is_empty() {
+                lines.push(make_line(font, std::mem::take(&mut line), false));
+                line_width = 0.0;
+            } else {
+                // The word alone is too wide: take as many characters as fit,
+                // but always at least one.
+                let mut fit = 1;
+                while fit < word.len() && width(font, None, &word[..fit + 1]) <= max_width {
+                    fit += 1;
+                }
+                lines.push(make_line(font, word[..fit].to_vec(), false));
+                rest = &rest[fit..];
+            }
+        }
+        lines.push(make_line(font, line, true));
+    }
+    lines
+}
+
+fn parse_markup(markup: &str, color: Color) -> Vec<(char, Color)> {
+    let mut chars = Vec::new();
+    let mut colors = vec![color];
+    let mut rest = markup;
+    while let Some(ch) = rest.chars().next() {
+        let current = *colors.last().unwrap();
+        if let Some(next) = rest.strip_prefix("[[") {
+            chars.push(('[', current));
+            rest = next;
+            continue;
+        }
+        if let Some(next) = rest.strip_prefix("[/color]") {
+            if colors.len() > 1 {
+                colors.pop();
+            }
+            rest = next;
+            continue;
+        }
+        if let Some(tag) = rest.strip_prefix("[color=")
+            && let Some((value, next)) = tag.split_once(']')
+            && let Ok(color) = value.parse()
+        {
+            colors.push(color);
+            rest = next;
+            continue;
+        }
+        chars.push((ch, current));
+        rest = &rest[ch.len_utf8()..];
+    }
+    chars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every character is 6 pixels wide.
+    struct Mono;
+
+    impl Font for Mono {
+        fn line_height(&self) -> f32 {
+            10.0
+        }
+
+        fn advance(&self, _ch: char) -> f32 {
+            6.0
+        }
+
+        fn draw_glyph<S>(&self, _surface: &mut S, _ch: char, _pos: Vec2, _color: Color)
+        where
+            S: Surface,
+        {
+        }
+    }
+
+    fn lay_out(text: &str, max_width: Option<f32>, align: TextAlign) -> TextLayout {
+        let options = LayoutOptions {
+            max_width,
+            align,
+            ..Default::default()
+        };
+        TextLayout::new(&Mono, text, Color::WHITE, &options)
+    }
+
+    fn line_glyphs(layout: &TextLayout) -> Vec<Range<usize>> {
+        layout
+            .lines()
+            .iter()
+            .map(|line| line.glyphs.clone())
+            .collect()
+    }
+
+    fn text(layout: &TextLayout, line: &LineBox) -> String {
+        layout.glyphs()[line.glyphs.clone()]
+            .iter()
+            .map(|g| g.ch)
+            .collect()
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let layout = lay_out("hello big world", Some(60.0), TextAlign::Left);
+        let lines: Vec<_> = layout
+            .lines()
+            .iter()
+            .map(|line| text(&layout, line))
+            .collect();
+        assert_eq!(lines, ["hellobig", "world"]);
+        assert_eq!(layout.lines()[1].pos, vec2(0.0, 10.0));
+        assert_eq!(layout.glyphs()[8].pos, vec2(0.0, 10.0));
+        assert_eq!(layout.size(), vec2(60.0, 20.0));
+    }
+
+    #[test]
+    fn splits_long_words() {
+        let layout = lay_out("abcdefg", Some(18.0), TextAlign::Left);
+        assert_eq!(line_glyphs(&layout), [0..3, 3..6, 6..7]);
+    }
+
+    #[test]
+    fn skips_whitespace_at_line_starts() {
+        let layout = lay_out("  ab", Some(6.0), TextAlign::Left);
+        assert_eq!(line_glyphs(&layout), [0..1, 1..2]);
+
+        let layout = lay_out("ab   cd", Some(18.0), TextAlign::Left);
+        assert_eq!(line_glyphs(&layout), [0..2, 2..4]);
+        assert_eq!(layout.glyphs()[2].pos.x, 0.0);
+    }
+
+    #[test]
+    fn non_positive_width_does_not_wrap() {
+        let text = "the quick brown fox";
+        let unbounded = lay_out(text, None, TextAlign::Center);
+        assert_eq!(unbounded.lines().len(), 1);
+        for max_width in [0.0, -10.0, f32::NAN] {
+            assert_eq!(lay_out(text, Some(max_width), TextAlign::Center), unbounded);
+        }
+    }
+
+    #[test]
+    fn alignment() {
+        let right = lay_out("ab\nabcd", Some(30.0), TextAlign::Right);
+        assert_eq!(right.lines()[0].pos.x, 18.0);
+        assert_eq!(right.lines()[1].pos.x, 6.0);
+
+        let justify = lay_out("a b c d", Some(36.0), TextAlign::Justify);
+        let xs: Vec<_> = justify.glyphs().iter().map(|g| g.pos.x).collect();
+        assert_eq!(xs, [0.0, 15.0, 30.0, 0.0]);
+    }
+}
@@ -0,0 +1,16 @@
+mod align;
+mod bitmap;
+mod bmfont;
+mod default_font;
+mod font;
+mod layout;
+#[cfg(feature = "ttf")]
+mod ttf;
+
+pub use align::TextAlign;
+pub use bitmap::{BitmapFont, draw_text};
+pub use bmfont::{BmFont, BmFontError, BmGlyph, draw_bmfont_text};
+pub use font::Font;
+pub use layout::{LayoutOptions, LineBox, PositionedGlyph, TextLayout};
+#[cfg(feature = "ttf")]
+pub use ttf::{FontError, TextOptions, TtfFace, TtfFont, draw_ttf_text};
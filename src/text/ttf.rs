@@ -0,0 +1,511 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use ab_glyph::{Font as _, FontVec, GlyphId, PxScale, ScaleFont, point};
+
+use crate::core::*;
+use crate::image::Image;
+
+use super::{Font, TextAlign};
+
+// Horizontal sub-pixel positions a glyph can be rasterized at.
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+// Rasterized glyphs kept by default before the cache starts over.
+const CACHE_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+pub enum FontError {
+    Invalid(ab_glyph::InvalidFont),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(_) => f.write_str("invalid TrueType/OpenType font data"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(error) => Some(error),
+        }
+    }
+}
+
+impl From<ab_glyph::InvalidFont> for FontError {
+    fn from(error: ab_glyph::InvalidFont) -> Self {
+        Self::Invalid(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOptions {
+    pub size: f32,
+    // Extra pixels between lines, as in `LayoutOptions`.
+    pub line_spacing: f32,
+    // `Justify` stretches the spaces of every line but the last to the width
+    // of the widest one.
+    pub align: TextAlign,
+    pub kerning: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            line_spacing: 0.0,
+            align: TextAlign::Left,
+            kerning: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    id: u16,
+    size: u32,
+    subpixel: u8,
+}
+
+struct GlyphTile {
+    image: Image,
+    offset: IVec2,
+}
+
+pub struct TtfFont {
+    font: FontVec,
+    cache: RefCell<HashMap<GlyphKey, Option<GlyphTile>>>,
+    cache_capacity: usize,
+}
+
+impl TtfFont {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, FontError> {
+        Ok(Self {
+            font: FontVec::try_from_vec(data)?,
+            cache: RefCell::new(HashMap::new()),
+            cache_capacity: CACHE_CAPACITY,
+        })
+    }
+
+    // Keeps at most `capacity` rasterized glyphs, counting every size and
+    // sub-pixel position separately. A full cache is cleared before the next
+    // glyph is added.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity.max(1);
+        self
+    }
+
+    pub fn open<P>(path: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_bytes(std::fs::read(path).unwrap()).unwrap()
+    }
+
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.font.as_scaled(PxScale::from(size)).ascent()
+    }
+
+    pub fn descent(&self, size: f32) -> f32 {
+        self.font.as_scaled(PxScale::from(size)).descent()
+    }
+
+    // Height of a line of text, not counting `TextOptions::line_spacing`.
+    pub fn line_height(&self, options: &TextOptions) -> f32 {
+        let font = self.font.as_scaled(PxScale::from(options.size));
+        font.height() + font.line_gap()
+    }
+
+    pub fn measure(&self, text: &str, options: &TextOptions) -> Vec2 {
+        let mut size = Vec2::ZERO;
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                size.y += options.line_spacing;
+            }
+            size.x = size.x.max(self.line_width(line, options));
+            size.y += self.line_height(options);
+        }
+        size
+    }
+
+    pub fn face(&self, options: TextOptions) -> TtfFace<'_> {
+        TtfFace {
+            font: self,
+            options,
+        }
+    }
+
+    pub fn cached_glyphs(&self) -> usize {
+        self.cache.borrow().len()
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    fn line_width(&self, line: &str, options: &TextOptions) -> f32 {
+        self.layout_line(line, options, 0.0, |_, _| {})
+    }
+
+    // Calls `glyph` with every glyph of `line` and its pen position and
+    // returns the advance of the whole line, with `gap` added after spaces.
+    fn layout_line<F>(&self, line: &str, options: &TextOptions, gap: f32, mut glyph: F) -> f32
+    where
+        F: FnMut(GlyphId, f32),
+    {
+        let font = self.font.as_scaled(PxScale::from(options.size));
+        let mut pen = 0.0;
+        let mut prev = None;
+        for ch in line.chars().filter(|ch| *ch != '\r') {
+            let id = font.glyph_id(ch);
+            if options.kerning
+                && let Some(prev) = prev
+            {
+                pen += font.kern(prev, id);
+            }
+            glyph(id, pen);
+            pen += font.h_advance(id);
+            if ch == ' ' {
+                pen += gap;
+            }
+            prev = Some(id);
+        }
+        pen
+    }
+
+    fn draw_glyph_id<S>(&self, surface: &mut S, id: GlyphId, pen: Vec2, size: f32, color: Color)
+    where
+        S: Surface,
+    {
+        let key = GlyphKey {
+            id: id.0,
+            size: (size * 64.0).round() as u32,
+            subpixel: ((pen.x - pen.x.floor()) * SUBPIXEL_STEPS) as u8,
+        };
+        let mut cache = self.cache.borrow_mut();
+        if cache.len() >= self.cache_capacity && !cache.contains_key(&key) {
+            cache.clear();
+        }
+        let tile = cache.entry(key).or_insert_with(|| self.rasterize(key));
+        if let Some(tile) = tile {
+            let options = BlitOptions {
+                blend: BlendMode::SourceOver,
+                tint: Some(color),
+                ..Default::default()
+            };
+            let dst = ivec2(pen.x.floor() as i32, pen.y.round() as i32) + tile.offset;
+            surface.blit_with(&tile.image, None, None, Some(dst), None, options);
+        }
+    }
+
+    fn rasterize(&self, key: GlyphKey) -> Option<GlyphTile> {
+        let scale = PxScale::from(key.size as f32 / 64.0);
+        let x = key.subpixel as f32 / SUBPIXEL_STEPS;
+        let glyph = GlyphId(key.id).with_scale_and_position(scale, point(x, 0.0));
+        let outline = self.font.outline_glyph(glyph)?;
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let mut image = Image::new(width, height, Color::TRANSPARENT);
+        outline.draw(|x, y, coverage| {
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            image.set_pixel(ivec2(x as i32, y as i32), Color::WHITE.with_a(alpha));
+        });
+        Some(GlyphTile {
+            image,
+            offset: ivec2(bounds.min.x as i32, bounds.min.y as i32),
+        })
+    }
+}
+
+pub struct TtfFace<'a> {
+    font: &'a TtfFont,
+    options: TextOptions,
+}
+
+impl Font for TtfFace<'_> {
+    fn line_height(&self) -> f32 {
+        self.font.line_height(&self.options)
+    }
+
+    fn advance(&self, ch: char) -> f32 {
+        let font = self.font.font.as_scaled(PxScale::from(self.options.size));
+        font.h_advance(font.glyph_id(ch))
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        if !self.options.kerning {
+            return 0.0;
+        }
+        let font = self.font.font.as_scaled(PxScale::from(self.options.size));
+        font.kern(font.glyph_id(first), font.glyph_id(second))
+    }
+
+    fn draw_glyph<S>(&self, surface: &mut S, ch: char, pos: Vec2, color: Color)
+    where
+        S: Surface,
+    {
+        let size = self.options.size;
+        let baseline = pos + vec2(0.0, self.font.ascent(size));
+        let id = self.font.font.glyph_id(ch);
+        self.font.draw_glyph_id(surface, id, baseline, size, color);
+    }
+}
+
+pub fn draw_ttf_text<S>(
+    surface: &mut S,
+    font: &TtfFont,
+    pos: Vec2,
+    color: Color,
+    text: &str,
+    options: &TextOptions,
+) where
+    S: Surface,
+{
+    let justify = options.align == TextAlign::Justify;
+    let width = if justify {
+        font.measure(text, options).x
+    } else {
+        0.0
+    };
+    let line_height = font.line_height(options) + options.line_spacing;
+    let mut baseline = pos.y + font.ascent(options.size);
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let line_width = font.line_width(line, options);
+        let spaces = line.matches(' ').count();
+        let gap = if justify && spaces > 0 && lines.peek().is_some() {
+            (width - line_width) / spaces as f32
+        } else {
+            0.0
+        };
+        let x = pos.x + options.align.offset(line_width);
+        font.layout_line(line, options, gap, |id, pen| {
+            font.draw_glyph_id(surface, id, vec2(x + pen, baseline), options.size, color);
+        });
+        baseline += line_height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{LayoutOptions, TextLayout};
+
+    // Cantarell Regular, under the SIL Open Font License 1.1 embedded in the
+    // font's name table.
+    const FONT: &[u8] = include_bytes!("fonts/Cantarell-Regular.ttf");
+
+    fn font() -> TtfFont {
+        TtfFont::from_bytes(FONT.to_vec()).unwrap()
+    }
+
+    fn options(align: TextAlign, line_spacing: f32) -> TextOptions {
+        TextOptions {
+            size: 20.0,
+            line_spacing,
+            align,
+            ..Default::default()
+        }
+    }
+
+    fn render(font: &TtfFont, pos: Vec2, text: &str, options: &TextOptions) -> Image {
+        let mut image = Image::new(200, 120, Color::TRANSPARENT);
+        draw_ttf_text(&mut image, font, pos, Color::WHITE, text, options);
+        image
+    }
+
+    // The smallest rectangle holding every drawn pixel within `area`.
+    fn ink(image: &Image, area: Rect) -> Rect {
+        let (mut min, mut max) = (area.max(), area.min());
+        for y in area.min().y..area.max().y {
+            for x in area.min().x..area.max().x {
+                if image.get_pixel(ivec2(x, y)).a() != 0 {
+                    min = min.min(ivec2(x, y));
+                    max = max.max(ivec2(x + 1, y + 1));
+                }
+            }
+        }
+        Rect::from_min_max(min, max.max(min))
+    }
+
+    fn whole(image: &Image) -> Rect {
+        Rect::from_size(image.size())
+    }
+
+    #[test]
+    fn invalid_data() {
+        let error = TtfFont::from_bytes(vec![0; 64]).err().unwrap();
+        assert_eq!(error.to_string(), "invalid TrueType/OpenType font data");
+    }
+
+    #[test]
+    fn measure() {
+        let font = font();
+        let options = options(TextAlign::Left, 0.0);
+        let line_height = font.line_height(&options);
+        let hello = font.measure("Hello", &options);
+        assert!(hello.x > 0.0);
+        assert_eq!(hello.y, line_height);
+
+        let two = font.measure("Hello\nHi", &options);
+        assert_eq!(two, vec2(hello.x, line_height * 2.0));
+        // A trailing newline starts another, empty line.
+        assert_eq!(font.measure("Hello\n", &options), two);
+        assert_eq!(font.measure("Hello\r\n", &options), two);
+
+        let spaced = font.measure(
+            "Hello\nHi\n",
+            &TextOptions {
+                line_spacing: 5.0,
+                ..options
+            },
+        );
+        assert_eq!(spaced, vec2(hello.x, line_height * 3.0 + 10.0));
+    }
+
+    #[test]
+    fn measure_matches_layout() {
+        let font = font();
+        for line_spacing in [0.0, 4.0, -2.0] {
+            let options = options(TextAlign::Left, line_spacing);
+            let layout_options = LayoutOptions {
+                line_spacing,
+                ..Default::default()
+            };
+            let text = "AVAST ye\nWorld, To\nfly";
+            let layout = TextLayout::new(&font.face(options), text, Color::WHITE, &layout_options);
+            assert_eq!(layout.size(), font.measure(text, &options));
+        }
+    }
+
+    #[test]
+    fn draws_within_measure() {
+        let font = font();
+        let options = options(TextAlign::Left, 3.0);
+        let pos = vec2(10.0, 10.0);
+        for text in ["Hello", "Wave\njumpy", "AVAST\n\nglyph"] {
+            let image = render(&font, pos, text, &options);
+            let ink = ink(&image, whole(&image));
+            let size = font.measure(text, &options);
+            assert!(!ink.is_empty(), "{text:?}");
+            assert!(ink.min().cmpge(pos.as_ivec2()).all(), "{text:?}: {ink:?}");
+            assert!(
+                ink.max().as_vec2().cmple(pos + size + 1.0).all(),
+                "{text:?}: {ink:?} {size}"
+            );
+        }
+    }
+
+    #[test]
+    fn alignment() {
+        let font = font();
+        let text = "Hi there\nHello";
+        let left = render(
+            &font,
+            vec2(100.0, 10.0),
+            text,
+            &options(TextAlign::Left, 0.0),
+        );
+        let left = ink(&left, whole(&left));
+        assert!((left.min().x - 100).abs() <= 2, "{left:?}");
+
+        let right = render(
+            &font,
+            vec2(100.0, 10.0),
+            text,
+            &options(TextAlign::Right, 0.0),
+        );
+        let right = ink(&right, whole(&right));
+        assert!((right.max().x - 100).abs() <= 2, "{right:?}");
+
+        let center = render(
+            &font,
+            vec2(100.0, 10.0),
+            text,
+            &options(TextAlign::Center, 0.0),
+        );
+        let center = ink(&center, whole(&center));
+        let middle = (center.min().x + center.max().x) / 2;
+        assert!((middle - 100).abs() <= 2, "{center:?}");
+        assert_eq!(center.size.y, left.size.y);
+    }
+
+    #[test]
+    fn justify() {
+        let font = font();
+        let text = "a b c\nmuch longer line\nx y";
+        let pos = vec2(10.0, 10.0);
+        let width = font.measure(text, &options(TextAlign::Justify, 0.0)).x;
+        let line_height = font.line_height(&options(TextAlign::Justify, 0.0)) as i32;
+        let rows =
+            |line: i32| Rect::new(ivec2(0, 10 + line * line_height), ivec2(200, line_height));
+
+        let left = render(&font, pos, text, &options(TextAlign::Left, 0.0));
+        let justified = render(&font, pos, text, &options(TextAlign::Justify, 0.0));
+        // The first line is stretched to the widest one.
+        let short = ink(&left, rows(0));
+        let stretched = ink(&justified, rows(0));
+        assert_eq!(stretched.min().x, short.min().x);
+        assert!(short.max().x < 60, "{short:?}");
+        assert!(
+            (stretched.max().as_vec2().x - (10.0 + width)).abs() <= 2.0,
+            "{stretched:?}"
+        );
+        // The widest and the last line are left as they are.
+        for line in 1..3 {
+            assert_eq!(ink(&left, rows(line)), ink(&justified, rows(line)));
+        }
+    }
+
+    #[test]
+    fn line_spacing() {
+        let font = font();
+        let plain = render(
+            &font,
+            vec2(10.0, 10.0),
+            "H\nH",
+            &options(TextAlign::Left, 0.0),
+        );
+        let spaced = render(
+            &font,
+            vec2(10.0, 10.0),
+            "H\nH",
+            &options(TextAlign::Left, 7.0),
+        );
+        let (plain, spaced) = (ink(&plain, whole(&plain)), ink(&spaced, whole(&spaced)));
+        assert_eq!(spaced.min(), plain.min());
+        assert_eq!(spaced.max(), plain.max() + ivec2(0, 7));
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        let font = font();
+        let options = options(TextAlign::Left, 0.0);
+        render(&font, vec2(10.0, 10.0), "a", &options);
+        render(&font, vec2(10.0, 40.0), "a", &options);
+        assert_eq!(font.cached_glyphs(), 1);
+        font.clear_cache();
+        assert_eq!(font.cached_glyphs(), 0);
+
+        let text = "The quick brown fox\njumps over the lazy dog";
+        let expected = render(&font, vec2(10.0, 10.0), text, &options);
+        assert!(font.cached_glyphs() > 8);
+
+        let small = TtfFont::from_bytes(FONT.to_vec())
+            .unwrap()
+            .with_cache_capacity(8);
+        let first = render(&small, vec2(10.0, 10.0), text, &options);
+        assert!((1..=8).contains(&small.cached_glyphs()));
+        let second = render(&small, vec2(10.0, 10.0), text, &options);
+        assert!(small.cached_glyphs() <= 8);
+        assert_eq!(first.pixels(), expected.pixels());
+        assert_eq!(second.pixels(), expected.pixels());
+    }
+}
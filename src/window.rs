@@ -13,6 +13,7 @@ use winit::{
     window::{WindowAttributes, WindowId},
 };
 
+use crate::core::simd;
 use crate::core::*;
 
 #[derive(Debug, Clone, Copy)]
@@ -37,6 +38,7 @@ impl<'a> Default for Config<'a> {
 pub struct Buffer<'a> {
     inner: softbuffer::Buffer<'a, Rc<WinitWindow>, Rc<WinitWindow>>,
     size: IVec2,
+    clip: ClipStack,
 }
 
 impl<'a> Surface for Buffer<'a> {
@@ -53,8 +55,52 @@ impl<'a> Surface for Buffer<'a> {
         self.size
     }
 
+    fn clip_rect(&self) -> Rect {
+        self.clip.clip(Rect::from_size(self.size))
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.clip.push(rect, Rect::from_size(self.size));
+    }
+
+    fn pop_clip(&mut self) {
+        self.clip.pop();
+    }
+
+    fn row(&self, y: i32) -> Option<&[u32]> {
+        let start = usize::try_from(y).ok()? * self.size.x as usize;
+        self.inner.get(start..start + self.size.x as usize)
+    }
+
+    fn row_mut(&mut self, y: i32) -> Option<&mut [u32]> {
+        let start = usize::try_from(y).ok()? * self.size.x as usize;
+        self.inner.get_mut(start..start + self.size.x as usize)
+    }
+
+    fn pixels(&self) -> Option<&[u32]> {
+        Some(&self.inner)
+    }
+
+    fn pixels_mut(&mut self) -> Option<&mut [u32]> {
+        Some(&mut self.inner)
+    }
+
     fn clear(&mut self, color: Color) {
-        self.inner.fill(color.as_u32());
+        if self.clip.is_empty() {
+            simd::fill(&mut self.inner, color.as_u32());
+            return;
+        }
+        let clip = self.clip_rect();
+        if clip.is_empty() {
+            return;
+        }
+        for y in clip.min().y..clip.max().y {
+            let start = Self::index(ivec2(clip.pos.x, y), self.size.x) as usize;
+            simd::fill(
+                &mut self.inner[start..start + clip.size.x as usize],
+                color.as_u32(),
+            );
+        }
     }
 }
 
@@ -71,6 +117,7 @@ pub struct Window {
     surface: softbuffer::Surface<Rc<WinitWindow>, Rc<WinitWindow>>,
     size: IVec2,
     key_pressed: HashSet<KeyCode>,
+    fps: u32,
 }
 
 impl Window {
@@ -92,6 +139,7 @@ impl Window {
             surface,
             size: ivec2(config.width as i32, config.height as i32),
             key_pressed: HashSet::new(),
+            fps: 0,
         }
     }
 
@@ -108,10 +156,11 @@ impl Window {
         self.surface.resize(width, height).unwrap();
     }
 
-    pub fn buffer(&mut self) -> Buffer {
+    pub fn buffer(&mut self) -> Buffer<'_> {
         Buffer {
             inner: self.surface.buffer_mut().unwrap(),
             size: self.size,
+            clip: ClipStack::new(),
         }
     }
 
@@ -122,6 +171,10 @@ impl Window {
     pub fn is_key_pressed(&self, key: KeyCode) -> bool {
         self.key_pressed.contains(&key)
     }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
 }
 
 pub trait State {
@@ -190,15 +243,14 @@ where
                 }
             }
             WindowEvent::KeyboardInput { event, .. } => {
-                if let Some(window) = self.window.as_mut() {
-                    if let PhysicalKey::Code(code) = event.physical_key {
-                        if !event.repeat {
-                            if event.state.is_pressed() {
-                                window.key_pressed.insert(code);
-                            } else {
-                                window.key_pressed.remove(&code);
-                            }
-                        }
+                if let Some(window) = self.window.as_mut()
+                    && let PhysicalKey::Code(code) = event.physical_key
+                    && !event.repeat
+                {
+                    if event.state.is_pressed() {
+                        window.key_pressed.insert(code);
+                    } else {
+                        window.key_pressed.remove(&code);
                     }
                 }
             }
@@ -214,7 +266,7 @@ where
                     self.spend_time += dt;
                     while self.spend_time >= 1.0 {
                         self.spend_time -= 1.0;
-                        println!("FPS: {}", self.frames);
+                        window.fps = self.frames as u32;
                         self.frames = 0;
                     }
 